@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// The first line is used as the file name, so that short or odd names get exercised too
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let (name, source) = match text.find('\n') {
+        Some(newline) => (&text[..newline], &text[newline + 1..]),
+        None => ("", &text[..]),
+    };
+
+    for line in source.lines() {
+        let _ = vmcomp::parser::parse_line(line);
+    }
+
+    // Rendering the diagnostics must not panic either
+    if let Err(errors) = vmcomp::compile_source(name, source) {
+        errors.iter().for_each(|error| drop(error.to_string()));
+    };
+});
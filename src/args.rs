@@ -0,0 +1,64 @@
+use std::ops::Range;
+
+use crate::emulator::RAM_SIZE;
+use crate::error::Error;
+
+// Highest RAM or ROM address
+const MAX_ADDRESS: u16 = RAM_SIZE as u16 - 1;
+
+/// A usage error pointing to `--help`.
+pub fn usage(message: &str) -> Error {
+    Error::Usage(format!("{}\n\nFor more information, try '--help'.", message))
+}
+
+/// Splits `--option=value` into its name and value, so that both it and `--option value` work.
+pub fn split_option(arg: &str) -> (&str, Option<String>) {
+    match arg.find('=') {
+        Some(equals) if arg.starts_with("--") => (&arg[..equals], Some(String::from(&arg[equals + 1..]))),
+        _ => (arg, None),
+    }
+}
+
+/// A 16-bit word, which may also be given as a signed integer: `-1` is 65535.
+pub fn parse_word(option: &str, value: &str) -> Result<u16, Error> {
+    match value.parse::<i32>() {
+        Ok(number) if (-32768..=65535).contains(&number) => Ok(number as u16),
+        _ => Err(usage(&format!("{} expects a 16-bit number, received {}", option, value))),
+    }
+}
+
+/// An address of RAM or ROM, from 0 to 32767.
+pub fn parse_address(option: &str, value: &str) -> Result<u16, Error> {
+    parse_bounded(option, value, MAX_ADDRESS)
+}
+
+/// `ADDRESS=VALUE`, as in `--set 256=-1`.
+pub fn parse_assignment(option: &str, value: &str) -> Result<(u16, u16), Error> {
+    match value.find('=') {
+        Some(equals) => Ok((parse_address(option, &value[..equals])?, parse_word(option, &value[equals + 1..])?)),
+        None => Err(usage(&format!("{} expects ADDRESS=VALUE, received {}", option, value))),
+    }
+}
+
+/// `ADDRESS` or `START..END`, as in `--show 256..260`, the end being at most 32768.
+pub fn parse_range(option: &str, value: &str) -> Result<Range<u16>, Error> {
+    match value.find("..") {
+        Some(dots) => {
+            let start = parse_address(option, &value[..dots])?;
+            let end = parse_bounded(option, &value[dots + 2..], MAX_ADDRESS + 1)?;
+
+            Ok(start..end)
+        }
+        None => {
+            let address = parse_address(option, value)?;
+            Ok(address..address + 1)
+        }
+    }
+}
+
+fn parse_bounded(option: &str, value: &str, max: u16) -> Result<u16, Error> {
+    match value.parse::<u16>() {
+        Ok(address) if address <= max => Ok(address),
+        _ => Err(usage(&format!("{} must be an address between 0 and {}, received {}", option, max, value))),
+    }
+}
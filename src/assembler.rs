@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use crate::diagnostic::{Diagnostic, Location};
+use crate::Diagnostics;
+
+// First RAM address given to variables
+const VARIABLE_BASE: u16 = 16;
+
+const PREDEFINED_SYMBOLS: [(&str, u16); 7] = [
+    ("SP", 0),
+    ("LCL", 1),
+    ("ARG", 2),
+    ("THIS", 3),
+    ("THAT", 4),
+    ("SCREEN", 16384),
+    ("KBD", 24576),
+];
+
+// A variable and the RAM address it was given
+type Variable = (String, u16);
+
+enum Line<'a> {
+    Address(&'a str),
+    Compute(&'a str),
+}
+
+/// Translates Hack assembly into machine words, one per instruction.
+pub fn assemble(file_name: &str, source: &str) -> Result<Vec<u16>, Diagnostics> {
+    assemble_with_variables(file_name, source).map(|(words, _)| words)
+}
+
+/// The RAM address given to each variable, in the order they are allocated.
+pub fn variables(file_name: &str, source: &str) -> Result<Vec<Variable>, Diagnostics> {
+    assemble_with_variables(file_name, source).map(|(_, variables)| variables)
+}
+
+fn assemble_with_variables(file_name: &str, source: &str) -> Result<(Vec<u16>, Vec<Variable>), Diagnostics> {
+    let mut symbols: HashMap<String, u16> = PREDEFINED_SYMBOLS.iter()
+        .map(|(name, address)| (String::from(*name), *address))
+        .chain((0..16).map(|register| (format!("R{}", register), register)))
+        .collect();
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+
+    // First pass: bind labels to the address of the instruction following them
+    for (index, raw_line) in source.lines().enumerate() {
+        let line = strip(raw_line);
+        let location = || Location::new(file_name, index + 1, column_of(raw_line, line), raw_line.trim_end());
+
+        if line.is_empty() {
+            continue;
+        } else if line.starts_with('(') {
+            let label = line.trim_start_matches('(').trim_end_matches(')');
+
+            if !line.ends_with(')') || !is_symbol(label) {
+                errors.push(Diagnostic::error("E0011", format!("invalid label declaration {}", line), Some(location())));
+            } else if symbols.contains_key(label) {
+                errors.push(Diagnostic::error("E0012", format!("symbol {} is defined twice", label), Some(location())));
+            } else {
+                symbols.insert(String::from(label), lines.len() as u16);
+            };
+        } else if let Some(value) = line.strip_prefix('@') {
+            lines.push((Line::Address(value), location()));
+        } else {
+            lines.push((Line::Compute(line), location()));
+        };
+    }
+
+    if lines.len() > 32768 {
+        errors.push(Diagnostic::error(
+            "E0013",
+            format!("program needs {} instructions but the ROM only holds 32768", lines.len()),
+            None,
+        ));
+    };
+
+    // Second pass: encode, allocating variables as they are first seen
+    let mut variables = Vec::new();
+    let mut words = Vec::new();
+
+    for (line, location) in lines {
+        let word = match line {
+            Line::Address(value) => match value.parse::<u16>() {
+                Ok(constant) if constant <= 32767 => Ok(constant),
+                Ok(_) => Err(format!("constant {} does not fit in 15 bits", value)),
+                Err(_) if is_symbol(value) => Ok(*symbols.entry(String::from(value)).or_insert_with(|| {
+                    let address = VARIABLE_BASE + variables.len() as u16;
+                    variables.push((String::from(value), address));
+                    address
+                })),
+                Err(_) => Err(format!("invalid A-instruction @{}", value)),
+            },
+            Line::Compute(instruction) => encode_compute(instruction),
+        };
+
+        match word {
+            Ok(word) => words.push(word),
+            Err(message) => errors.push(Diagnostic::error("E0010", message, Some(location))),
+        };
+    }
+
+    if errors.is_empty() {
+        Ok((words, variables))
+    } else {
+        Err(errors)
+    }
+}
+
+/// Reads the `.hack` text format: one 16-digit binary word per line.
+pub fn read_hack(file_name: &str, source: &str) -> Result<Vec<u16>, Diagnostics> {
+    let mut words = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let word = line.trim();
+
+        if word.is_empty() {
+            continue;
+        };
+
+        match u16::from_str_radix(word, 2) {
+            Ok(value) if word.len() == 16 => words.push(value),
+            _ => errors.push(Diagnostic::error(
+                "E0014",
+                format!("expected a 16-digit binary word, received {}", word),
+                Some(Location::new(file_name, index + 1, column_of(line, word), line.trim_end())),
+            )),
+        };
+    }
+
+    if errors.is_empty() {
+        Ok(words)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Formats machine words in the `.hack` text format.
+pub fn format_hack(words: &[u16]) -> Vec<String> {
+    words.iter().map(|word| format!("{:016b}\n", word)).collect()
+}
+
+// Removes comments and every space, as `D = M + 1` is a valid instruction
+fn strip(line: &str) -> &str {
+    match line.find("//") {
+        Some(comment) => line[..comment].trim(),
+        None => line.trim(),
+    }
+}
+
+fn column_of(line: &str, part: &str) -> std::ops::Range<usize> {
+    let start = line.find(part).unwrap_or(0);
+    start..start + part.len()
+}
+
+fn is_symbol(text: &str) -> bool {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || "_.$:".contains(c);
+
+    match text.chars().next() {
+        Some(first) => !first.is_ascii_digit() && text.chars().all(valid_char),
+        None => false,
+    }
+}
+
+fn encode_compute(instruction: &str) -> Result<u16, String> {
+    let instruction: String = instruction.chars().filter(|c| !c.is_whitespace()).collect();
+
+    let (dest, rest) = match instruction.find('=') {
+        Some(equals) => (&instruction[..equals], &instruction[equals + 1..]),
+        None => ("", &instruction[..]),
+    };
+    let (comp, jump) = match rest.find(';') {
+        Some(semicolon) => (&rest[..semicolon], &rest[semicolon + 1..]),
+        None => (rest, ""),
+    };
+
+    let comp_bits = match encode_comp(comp) {
+        Some(bits) => bits,
+        None => return Err(format!("invalid computation {} in {}", comp, instruction)),
+    };
+    let dest_bits = match encode_dest(dest) {
+        Some(bits) => bits,
+        None => return Err(format!("invalid destination {} in {}", dest, instruction)),
+    };
+    let jump_bits = match encode_jump(jump) {
+        Some(bits) => bits,
+        None => return Err(format!("invalid jump {} in {}", jump, instruction)),
+    };
+
+    Ok(0b1110_0000_0000_0000 | comp_bits << 6 | dest_bits << 3 | jump_bits)
+}
+
+// The `a` bit followed by the six ALU control bits
+fn encode_comp(comp: &str) -> Option<u16> {
+    let (a, comp) = if comp.contains('M') {
+        (1, comp.replace('M', "A"))
+    } else {
+        (0, String::from(comp))
+    };
+
+    let bits = match comp.as_str() {
+        "0" => 0b101010,
+        "1" => 0b111111,
+        "-1" => 0b111010,
+        "D" => 0b001100,
+        "A" => 0b110000,
+        "!D" => 0b001101,
+        "!A" => 0b110001,
+        "-D" => 0b001111,
+        "-A" => 0b110011,
+        "D+1" | "1+D" => 0b011111,
+        "A+1" | "1+A" => 0b110111,
+        "D-1" => 0b001110,
+        "A-1" => 0b110010,
+        "D+A" | "A+D" => 0b000010,
+        "D-A" => 0b010011,
+        "A-D" => 0b000111,
+        "D&A" | "A&D" => 0b000000,
+        "D|A" | "A|D" => 0b010101,
+        _ => return None,
+    };
+
+    Some(a << 6 | bits)
+}
+
+fn encode_dest(dest: &str) -> Option<u16> {
+    let mut bits = 0;
+
+    for register in dest.chars() {
+        let bit = match register {
+            'A' => 0b100,
+            'D' => 0b010,
+            'M' => 0b001,
+            _ => return None,
+        };
+
+        if bits & bit != 0 {
+            return None;
+        };
+
+        bits |= bit;
+    }
+
+    Some(bits)
+}
+
+fn encode_jump(jump: &str) -> Option<u16> {
+    match jump {
+        "" => Some(0b000),
+        "JGT" => Some(0b001),
+        "JEQ" => Some(0b010),
+        "JGE" => Some(0b011),
+        "JLT" => Some(0b100),
+        "JNE" => Some(0b101),
+        "JLE" => Some(0b110),
+        "JMP" => Some(0b111),
+        _ => None,
+    }
+}
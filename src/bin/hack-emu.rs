@@ -0,0 +1,135 @@
+use std::env;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::process;
+
+use vmcomp::args::{parse_address, parse_assignment, parse_range, split_option, usage};
+use vmcomp::emulator::{Cpu, Stop};
+use vmcomp::error::Error;
+
+const HELP: &str = "\
+Runs a Hack program (.asm or .hack) on an emulated Hack CPU
+
+Usage: hack-emu [OPTIONS] <FILE>
+
+Options:
+      --cycles <N>           Stop after N cycles (default: 1000000)
+      --break <ADDRESS>      Stop when the program counter reaches ADDRESS
+      --set <ADDRESS=VALUE>  Store VALUE in RAM[ADDRESS] before running
+      --show <ADDRESS[..END]>
+                             Print RAM[ADDRESS], or RAM[ADDRESS..END], once stopped
+                             (default: 0..16)
+  -h, --help                 Print this help and exit";
+
+struct Options {
+    file: String,
+    cycles: u64,
+    breakpoints: Vec<u16>,
+    assignments: Vec<(u16, u16)>,
+    shown: Vec<Range<u16>>,
+}
+
+fn parse_args() -> Result<Option<Options>, Error> {
+    let mut args = env::args().skip(1);
+    let mut file = None;
+    let mut options = Options {
+        file: String::new(),
+        cycles: 1_000_000,
+        breakpoints: Vec::new(),
+        assignments: Vec::new(),
+        shown: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            file = Some(arg);
+            continue;
+        };
+
+        if arg == "-h" || arg == "--help" {
+            return Ok(None);
+        };
+
+        let (name, inline_value) = split_option(&arg);
+        let value = match inline_value.or_else(|| args.next()) {
+            Some(value) => value,
+            None => return Err(usage(&format!("{} requires a value", name))),
+        };
+
+        match name {
+            "--cycles" => options.cycles = match value.parse() {
+                Ok(cycles) => cycles,
+                Err(_) => return Err(usage(&format!("--cycles expects a number, received {}", value))),
+            },
+            "--break" => options.breakpoints.push(parse_address(name, &value)?),
+            "--set" => options.assignments.push(parse_assignment(name, &value)?),
+            "--show" => options.shown.push(parse_range(name, &value)?),
+            _ => return Err(usage(&format!("unknown option {}", arg))),
+        };
+    }
+
+    options.file = match file {
+        Some(file) => file,
+        None => return Err(usage("no program given")),
+    };
+
+    if options.shown.is_empty() {
+        options.shown.push(0..16);
+    };
+
+    Ok(Some(options))
+}
+
+fn run() -> Result<(), Error> {
+    let options = match parse_args()? {
+        Some(options) => options,
+        None => {
+            println!("{}", HELP);
+            return Ok(());
+        }
+    };
+
+    let path = Path::new(&options.file);
+    let source = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+
+    let loaded = if options.file.ends_with(".hack") {
+        Cpu::from_hack(&options.file, &source)
+    } else {
+        Cpu::from_asm(&options.file, &source)
+    };
+
+    let mut cpu = match loaded {
+        Ok(cpu) => cpu,
+        Err(diagnostics) => {
+            diagnostics.iter().for_each(|diagnostic| eprintln!("{}", diagnostic));
+            return Err(Error::Translation { target: options.file.clone(), error_count: diagnostics.len() });
+        }
+    };
+
+    options.assignments.iter().for_each(|(address, value)| cpu.set_ram(*address, *value));
+    options.breakpoints.iter().for_each(|address| cpu.add_breakpoint(*address));
+
+    match cpu.run(options.cycles) {
+        Stop::CycleLimit => println!("Stopped after reaching the limit of {} cycles", cpu.cycles),
+        Stop::Breakpoint(address) => println!("Stopped at breakpoint {} after {} cycles", address, cpu.cycles),
+        Stop::Halted => println!("Halted after {} cycles", cpu.cycles),
+    };
+
+    println!("PC={} A={} D={}", cpu.pc, cpu.a as i16, cpu.d as i16);
+
+    for range in options.shown {
+        for address in range {
+            println!("RAM[{}] = {}", address, cpu.ram(address) as i16);
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        process::exit(err.exit_code());
+    }
+}
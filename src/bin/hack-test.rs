@@ -0,0 +1,112 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use vmcomp::error::Error;
+use vmcomp::files;
+use vmcomp::test_script;
+
+const HELP: &str = "\
+Runs Nand2Tetris CPU emulator test scripts (.tst) against translated VM code
+
+Usage: hack-test <PATH>...
+
+Each PATH is a .tst file or a directory searched recursively for them.
+Scripts meant for the VM emulator (*VME.tst) are skipped.
+
+A script loading Foo.asm runs the translation of Foo.vm, or of every .vm
+file of its directory, and its output is compared with its compare-to file.";
+
+fn find_scripts(path: &Path, scripts: &mut Vec<PathBuf>) -> Result<(), Error> {
+    if !path.is_dir() {
+        scripts.push(path.to_path_buf());
+        return Ok(());
+    };
+
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)
+        .map_err(|err| Error::io(path, err))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort();
+
+    for entry in entries {
+        let name = entry.file_name().unwrap().to_string_lossy().into_owned();
+
+        if entry.is_dir() {
+            find_scripts(&entry, scripts)?;
+        } else if name.ends_with(".tst") && !name.ends_with("VME.tst") {
+            scripts.push(entry);
+        };
+    }
+
+    Ok(())
+}
+
+fn run_script(script: &Path) -> Result<(), String> {
+    let dir = script.parent().unwrap_or_else(|| Path::new("."));
+    let source = fs::read_to_string(script).map_err(|err| err.to_string())?;
+    let commands = test_script::parse_script(&source)?;
+
+    let run = test_script::run_script(&commands, &mut |name| test_script::load(dir, name))?;
+
+    if let Some(output_file) = &run.output_file {
+        let lines: Vec<String> = run.output.iter().map(|line| format!("{}\n", line)).collect();
+        files::write_lines(&dir.join(output_file), &lines).map_err(|err| err.to_string())?;
+    };
+
+    if let Some(compare_to) = &run.compare_to {
+        let path = dir.join(compare_to);
+        let expected = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+        test_script::compare(&run.output, &expected).map_err(|mismatch| mismatch.to_string())?;
+    };
+
+    Ok(())
+}
+
+fn run() -> Result<(), Error> {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", HELP);
+        return Ok(());
+    };
+
+    if args.is_empty() {
+        return Err(Error::Usage(String::from("Usage: hack-test <PATH>...")));
+    };
+
+    let mut scripts = Vec::new();
+
+    for arg in args.iter() {
+        find_scripts(Path::new(arg), &mut scripts)?;
+    }
+
+    let mut failed = 0;
+
+    for script in scripts.iter() {
+        match run_script(script) {
+            Ok(()) => println!("PASS {}", script.display()),
+            Err(reason) => {
+                failed += 1;
+                println!("FAIL {}\n{}", script.display(), reason);
+            }
+        };
+    }
+
+    println!("\n{} passed, {} failed", scripts.len() - failed, failed);
+
+    if failed > 0 {
+        return Err(Error::Test { failed, total: scripts.len() });
+    };
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        process::exit(err.exit_code());
+    }
+}
@@ -0,0 +1,166 @@
+use std::env;
+use std::ops::Range;
+use std::path::Path;
+use std::process;
+
+use vmcomp::args::{parse_assignment, parse_range, split_option, usage};
+use vmcomp::emulator::Stop;
+use vmcomp::error::Error;
+use vmcomp::files;
+use vmcomp::interpreter::Vm;
+use vmcomp::options::Bootstrap;
+
+const HELP: &str = "\
+Runs VM code (.vm files or directories) without translating it
+
+Usage: vm-emu [OPTIONS] <INPUT>...
+
+Options:
+      --steps <N>            Stop after N commands (default: 1000000)
+      --break <FUNCTION>     Stop when FUNCTION is entered
+      --set <ADDRESS=VALUE>  Store VALUE in RAM[ADDRESS] before running
+      --show <ADDRESS[..END]>
+                             Print RAM[ADDRESS], or RAM[ADDRESS..END], once stopped
+                             (default: 0..16)
+      --trace                Print every command as it executes
+      --no-bootstrap         Start at the first command instead of calling Sys.init
+  -h, --help                 Print this help and exit";
+
+struct Options {
+    inputs: Vec<String>,
+    steps: u64,
+    breakpoints: Vec<String>,
+    assignments: Vec<(u16, u16)>,
+    shown: Vec<Range<u16>>,
+    trace: bool,
+    translation: vmcomp::Options,
+}
+
+fn parse_args() -> Result<Option<Options>, Error> {
+    let mut args = env::args().skip(1);
+    let mut options = Options {
+        inputs: Vec::new(),
+        steps: 1_000_000,
+        breakpoints: Vec::new(),
+        assignments: Vec::new(),
+        shown: Vec::new(),
+        trace: false,
+        translation: vmcomp::Options::default(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--trace" => {
+                options.trace = true;
+                continue;
+            }
+            "--no-bootstrap" => {
+                options.translation.bootstrap = Bootstrap::Never;
+                continue;
+            }
+            _ if !arg.starts_with('-') => {
+                options.inputs.push(arg);
+                continue;
+            }
+            _ => (),
+        };
+
+        let (name, inline_value) = split_option(&arg);
+        let value = match inline_value.or_else(|| args.next()) {
+            Some(value) => value,
+            None => return Err(usage(&format!("{} requires a value", name))),
+        };
+
+        match name {
+            "--steps" => options.steps = match value.parse() {
+                Ok(steps) => steps,
+                Err(_) => return Err(usage(&format!("--steps expects a number, received {}", value))),
+            },
+            "--break" => options.breakpoints.push(value),
+            "--set" => options.assignments.push(parse_assignment(name, &value)?),
+            "--show" => options.shown.push(parse_range(name, &value)?),
+            _ => return Err(usage(&format!("unknown option {}", arg))),
+        };
+    }
+
+    if options.inputs.is_empty() {
+        return Err(usage("no input given"));
+    };
+
+    if options.shown.is_empty() {
+        options.shown.push(0..16);
+    };
+
+    Ok(Some(options))
+}
+
+fn run() -> Result<(), Error> {
+    let options = match parse_args()? {
+        Some(options) => options,
+        None => {
+            println!("{}", HELP);
+            return Ok(());
+        }
+    };
+
+    let mut sources = Vec::new();
+
+    for input in options.inputs.iter() {
+        sources.append(&mut files::read_input(Path::new(input))?);
+    }
+
+    let mut vm = match Vm::load(&sources, &options.translation) {
+        Ok(vm) => vm,
+        Err(diagnostics) => {
+            diagnostics.iter().for_each(|diagnostic| eprintln!("{}", diagnostic));
+            return Err(Error::Translation { target: options.inputs.join(" "), error_count: diagnostics.len() });
+        }
+    };
+
+    // Function names are only known once the inputs are loaded
+    if let Some(function_name) = options.breakpoints.iter().find(|function_name| !vm.defines(function_name)) {
+        return Err(usage(&format!("--break {} names a function no input defines", function_name)));
+    };
+
+    options.assignments.iter().for_each(|(address, value)| vm.set_ram(*address, *value));
+    options.breakpoints.iter().for_each(|function_name| vm.add_breakpoint(function_name));
+
+    // Tracing runs one command at a time
+    let budget = if options.trace { 1 } else { options.steps };
+    let stop = loop {
+        if options.trace {
+            if let Some(instruction) = vm.current() {
+                let location = &instruction.location;
+                println!("{}:{}: {:<30} SP={}", location.file, location.line, instruction.command.to_string(), vm.ram(0));
+            };
+        };
+
+        let stop = vm.run(budget.min(options.steps - vm.steps)).map_err(Error::Runtime)?;
+
+        if stop != Stop::CycleLimit || vm.steps >= options.steps {
+            break stop;
+        };
+    };
+
+    match stop {
+        Stop::CycleLimit => println!("Stopped after reaching the limit of {} steps", vm.steps),
+        Stop::Breakpoint(_) => println!("Stopped on entering {} after {} steps", vm.function_name().unwrap_or("?"), vm.steps),
+        Stop::Halted => println!("Halted after {} steps", vm.steps),
+    };
+
+    for range in options.shown {
+        for address in range {
+            println!("RAM[{}] = {}", address, vm.ram(address) as i16);
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        process::exit(err.exit_code());
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::command::{Command, Segment};
+use crate::diagnostic::{Diagnostic, Location};
+use crate::parser::Instruction;
+use crate::{Diagnostics, ParsedFile};
+
+// Statics live in RAM[16..256], below the stack
+pub(crate) const STATIC_SLOTS: usize = 240;
+
+// Labels only exist within the function defining them, so every goto and
+// if-goto must target a label of its own function
+pub(crate) fn check_labels(file: &ParsedFile) -> Diagnostics {
+    let mut diagnostics = Vec::new();
+
+    for (function_name, body) in function_bodies(file) {
+        let mut defined: HashMap<&str, &Location> = HashMap::new();
+
+        for instruction in body {
+            if let Command::Label(label) = &instruction.command {
+                match defined.get(label.as_str()) {
+                    Some(previous) => diagnostics.push(Diagnostic::error(
+                        "E0009",
+                        format!("label {} is defined twice in {}", label, function_name),
+                        Some(instruction.location.clone()),
+                    ).with_help(format!("first defined on line {}", previous.line))),
+                    None => {
+                        defined.insert(label, &instruction.location);
+                    }
+                };
+            };
+        }
+
+        for instruction in body {
+            match &instruction.command {
+                Command::Goto(label) | Command::IfGoto(label) if !defined.contains_key(label.as_str()) => {
+                    diagnostics.push(Diagnostic::error(
+                        "E0008",
+                        format!("label {} is not defined in {}", label, function_name),
+                        Some(instruction.location.clone()),
+                    ).with_help(String::from("labels are scoped to the function declaring them")));
+                }
+                _ => (),
+            };
+        }
+    }
+
+    diagnostics
+}
+
+// Each distinct `static i` of a file takes one of the slots shared by the whole program
+pub(crate) fn check_statics(file: &ParsedFile) -> Diagnostics {
+    let mut used = HashSet::new();
+
+    for instruction in file.instructions.iter() {
+        match &instruction.command {
+            Command::Push(Segment::Static, index) | Command::Pop(Segment::Static, index) => {
+                used.insert(*index);
+
+                if used.len() > STATIC_SLOTS {
+                    return vec![Diagnostic::warning(
+                        "W0001",
+                        format!("{} uses more than {} static variables", file.name, STATIC_SLOTS),
+                        Some(instruction.location.clone()),
+                    ).with_help(String::from("static variables past RAM[255] overwrite the stack"))];
+                };
+            }
+            _ => (),
+        };
+    }
+
+    Vec::new()
+}
+
+// Files that each fit can still fill the slots together
+pub(crate) fn check_static_total(files: &[ParsedFile]) -> Diagnostics {
+    let counts: Vec<usize> = files.iter().map(static_count).collect();
+    let total: usize = counts.iter().sum();
+
+    if total <= STATIC_SLOTS || counts.iter().any(|count| *count > STATIC_SLOTS) {
+        return Vec::new();
+    };
+
+    vec![Diagnostic::warning(
+        "W0001",
+        format!("the program uses {} static variables, more than the {} that fit", total, STATIC_SLOTS),
+        None,
+    ).with_help(String::from("static variables past RAM[255] overwrite the stack"))]
+}
+
+fn static_count(file: &ParsedFile) -> usize {
+    let used: HashSet<u16> = file.instructions.iter().filter_map(|instruction| match instruction.command {
+        Command::Push(Segment::Static, index) | Command::Pop(Segment::Static, index) => Some(index),
+        _ => None,
+    }).collect();
+
+    used.len()
+}
+
+// The link step: every call must reach exactly one function, always with the same
+// argument count. `entry` is the function the bootstrap code calls, if any.
+pub(crate) fn check_functions(files: &[ParsedFile], entry: Option<&str>) -> Diagnostics {
+    let mut diagnostics = Vec::new();
+    let mut defined: HashMap<&str, &Location> = HashMap::new();
+
+    for instruction in files.iter().flat_map(|file| file.instructions.iter()) {
+        if let Command::Function(name, _) = &instruction.command {
+            match defined.get(name.as_str()) {
+                Some(previous) => diagnostics.push(Diagnostic::error(
+                    "E0016",
+                    format!("function {} is defined twice", name),
+                    Some(instruction.location.clone()),
+                ).with_help(format!("first defined on line {} of {}", previous.line, previous.file))),
+                None => {
+                    defined.insert(name, &instruction.location);
+                }
+            };
+        };
+    }
+
+    let mut called: HashMap<&str, (u16, &Location)> = HashMap::new();
+
+    for instruction in files.iter().flat_map(|file| file.instructions.iter()) {
+        if let Command::Call(name, arg_count) = &instruction.command {
+            if !defined.contains_key(name.as_str()) {
+                let mut diagnostic = Diagnostic::error(
+                    "E0015",
+                    format!("function {} is not defined", name),
+                    Some(instruction.location.clone()),
+                );
+
+                if let Some(similar) = similar_name(name, defined.keys().copied()) {
+                    diagnostic = diagnostic.with_help(format!("a function named {} exists", similar));
+                };
+
+                diagnostics.push(diagnostic);
+            };
+
+            match called.get(name.as_str()) {
+                Some((previous_count, previous)) if previous_count != arg_count => diagnostics.push(Diagnostic::warning(
+                    "W0002",
+                    format!("{} is called with {} argument(s) here, but with {} elsewhere", name, arg_count, previous_count),
+                    Some(instruction.location.clone()),
+                ).with_help(format!("called with {} on line {} of {}", previous_count, previous.line, previous.file))),
+                Some(_) => (),
+                None => {
+                    called.insert(name, (*arg_count, &instruction.location));
+                }
+            };
+        };
+    }
+
+    if let Some(entry) = entry.filter(|entry| !defined.contains_key(entry)) {
+        diagnostics.push(Diagnostic::error(
+            "E0015",
+            format!("function {} is not defined", entry),
+            None,
+        ).with_help(String::from("the bootstrap code calls it to start the program")));
+    };
+
+    diagnostics
+}
+
+// The defined name closest to a misspelt one, at most two edits away
+fn similar_name<'a>(name: &str, defined: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    defined
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+// Splits a file at each `function` command; what precedes the first one belongs to the class
+pub(crate) fn function_bodies(file: &ParsedFile) -> Vec<(&str, &[Instruction])> {
+    let mut bodies = Vec::new();
+    let mut name = file.class_name.as_str();
+    let mut start = 0;
+
+    for (index, instruction) in file.instructions.iter().enumerate() {
+        if let Command::Function(function_name, _) = &instruction.command {
+            bodies.push((name, &file.instructions[start..index]));
+            name = function_name;
+            start = index;
+        };
+    }
+
+    bodies.push((name, &file.instructions[start..]));
+
+    bodies
+}
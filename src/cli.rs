@@ -0,0 +1,319 @@
+use std::path::{Path, PathBuf};
+
+use vmcomp::args::{parse_address, split_option, usage};
+use vmcomp::error::Error;
+use vmcomp::manifest::{Manifest, MANIFEST_NAME};
+use vmcomp::options::Bootstrap;
+
+pub const HELP: &str = "\
+Translates Hack VM code (.vm) into Hack assembly (.asm)
+
+Usage: vmcomp [OPTIONS] <INPUT>...
+
+Each INPUT is a .vm file or a directory containing .vm files. Files are linked
+in the order given, the files of a directory sorted by name, and each only once.
+Without any INPUT, the sources and settings come from ./vmcomp.toml.
+
+Options:
+  -i, --input <INPUT>  Add INPUT after the previous ones, e.g. `-i Sys.vm -i .`
+                       to link Sys.vm first
+  -r, --recursive      Also look for .vm files in subdirectories
+  -L, --library <DIR>  Link the .vm files of DIR after the inputs, except those
+                       named like an input file
+      --manifest <FILE>
+                       Read sources and settings from the manifest FILE
+  -o, --output <FILE>  Write the output to FILE
+                       (default: Foo.vm -> Foo.asm, Dir -> Dir/Dir.asm)
+      --emit <KIND>    Output Hack assembly (asm) or machine code (hack)
+                       (default: asm)
+      --keep-going     Write the output even if some lines failed to translate
+  -O, --optimize       Shorten the output with a peephole optimizer, and report
+                       the instruction counts before and after
+      --bootstrap      Always start with the bootstrap code calling the entry function
+      --no-bootstrap   Never emit the bootstrap code
+                       (default: only when a file defines the entry function)
+      --entry <NAME>   Function the bootstrap code calls (default: Sys.init)
+      --remove-dead-functions
+                       Leave out the functions nothing reachable from the entry
+                       function calls, and report them
+      --keep <NAME>    Keep the function NAME even if unreachable, e.g. when only
+                       a computed jump reaches it
+      --report-statics
+                       Print the RAM address of each static variable
+      --shared-calls   Call and return through shared $$CALL and $$RETURN routines:
+                       smaller but slower code
+      --sp <N>         Initial value of SP (default: 256)
+      --lcl <N>        Initial value of LCL (default: 512)
+      --arg <N>        Initial value of ARG (default: 768)
+      --this <N>       Initial value of THIS (default: 1024)
+      --that <N>       Initial value of THAT (default: 1280)
+  -h, --help           Print this help and exit
+  -V, --version        Print the version and exit";
+
+pub enum Action {
+    Help,
+    Version,
+    Translate(Options),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    Asm,
+    Hack,
+}
+
+pub struct Options {
+    pub inputs: Vec<PathBuf>,
+    pub libraries: Vec<PathBuf>,
+    pub recursive: bool,
+    pub output: PathBuf,
+    pub emit: Emit,
+    pub keep_going: bool,
+    pub optimize: bool,
+    pub report_statics: bool,
+    pub translation: vmcomp::Options,
+}
+
+pub fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Action, Error> {
+    let mut inputs = Vec::new();
+    let mut libraries = Vec::new();
+    let mut recursive = false;
+    let mut manifest_path = None;
+    let mut output = None;
+    let mut emit = None;
+    let mut keep_going = false;
+    let mut optimize = false;
+    let mut report_statics = false;
+    let mut bootstrap = None;
+    let mut entry = None;
+    let mut translation = vmcomp::Options::default();
+    let mut only_inputs = false;
+
+    let mut args = args.skip(1);
+
+    while let Some(arg) = args.next() {
+        if only_inputs || !arg.starts_with('-') || arg == "-" {
+            inputs.push(PathBuf::from(arg));
+            continue;
+        };
+
+        let (name, inline_value) = split_option(&arg);
+
+        match name {
+            "-h" | "--help" => return Ok(Action::Help),
+            "-V" | "--version" => return Ok(Action::Version),
+            "--keep-going" => keep_going = true,
+            "-O" | "--optimize" => optimize = true,
+            "--report-statics" => report_statics = true,
+            "--bootstrap" => bootstrap = Some(Bootstrap::Always),
+            "--no-bootstrap" => bootstrap = Some(Bootstrap::Never),
+            "-r" | "--recursive" => recursive = true,
+            "--shared-calls" => translation.shared_calls = true,
+            "--remove-dead-functions" => translation.remove_dead_functions = true,
+            "-i" | "--input" => match inline_value.or_else(|| args.next()) {
+                Some(value) => inputs.push(PathBuf::from(value)),
+                None => return Err(usage(&format!("{} requires a file or directory", name))),
+            },
+            "-L" | "--library" => match inline_value.or_else(|| args.next()) {
+                Some(value) => libraries.push(PathBuf::from(value)),
+                None => return Err(usage(&format!("{} requires a directory", name))),
+            },
+            "--manifest" => match inline_value.or_else(|| args.next()) {
+                Some(value) => manifest_path = Some(PathBuf::from(value)),
+                None => return Err(usage(&format!("{} requires a file name", name))),
+            },
+            "--keep" => match inline_value.or_else(|| args.next()) {
+                Some(value) => translation.keep.push(value),
+                None => return Err(usage(&format!("{} requires a function name", name))),
+            },
+            "--entry" => match inline_value.or_else(|| args.next()) {
+                Some(value) => entry = Some(value),
+                None => return Err(usage(&format!("{} requires a function name", name))),
+            },
+            "-o" | "--output" => {
+                let value = match inline_value.or_else(|| args.next()) {
+                    Some(value) => value,
+                    None => return Err(usage(&format!("{} requires a file name", name))),
+                };
+                output = Some(PathBuf::from(value));
+            }
+            "--emit" => match inline_value.or_else(|| args.next()) {
+                Some(value) => emit = Some(parse_emit(&value)?),
+                None => return Err(usage("--emit requires asm or hack")),
+            },
+            "--sp" | "--lcl" | "--arg" | "--this" | "--that" => {
+                let value = match inline_value.or_else(|| args.next()) {
+                    Some(value) => value,
+                    None => return Err(usage(&format!("{} requires an address", name))),
+                };
+                let address = parse_address(name, &value)?;
+                let registers = &mut translation.registers;
+
+                match name {
+                    "--sp" => registers.sp = address,
+                    "--lcl" => registers.lcl = address,
+                    "--arg" => registers.arg = address,
+                    "--this" => registers.this = address,
+                    _ => registers.that = address,
+                };
+            }
+            "--" => only_inputs = true,
+            _ => return Err(usage(&format!("unknown option {}", arg))),
+        };
+    }
+
+    if manifest_path.is_none() && inputs.is_empty() && Path::new(MANIFEST_NAME).is_file() {
+        manifest_path = Some(PathBuf::from(MANIFEST_NAME));
+    };
+
+    // The command line takes precedence over the manifest
+    if let Some(path) = manifest_path {
+        let manifest = Manifest::read(&path)?;
+        let settings = manifest.output;
+
+        inputs = manifest.sources.into_iter().chain(inputs).collect();
+        libraries = manifest.libraries.into_iter().chain(libraries).collect();
+        recursive |= manifest.recursive;
+        output = output.or(settings.path);
+        entry = entry.or(manifest.entry);
+        optimize |= settings.optimize.unwrap_or(false);
+        translation.shared_calls |= settings.shared_calls.unwrap_or(false);
+        translation.remove_dead_functions |= settings.remove_dead_functions.unwrap_or(false);
+        translation.keep = manifest.keep.into_iter().chain(translation.keep).collect();
+
+        if emit.is_none() {
+            emit = settings.emit.as_deref().map(parse_emit).transpose()?;
+        };
+
+        if bootstrap.is_none() {
+            bootstrap = settings.bootstrap.map(|always| if always { Bootstrap::Always } else { Bootstrap::Never });
+        };
+    };
+
+    let emit = emit.unwrap_or(Emit::Asm);
+    translation.bootstrap = bootstrap.unwrap_or(translation.bootstrap);
+    translation.entry = entry.unwrap_or(translation.entry);
+
+    let output = match (output, inputs.len()) {
+        (Some(output), _) => output,
+        (None, 0) => return Err(usage("no input given")),
+        (None, 1) => default_output(&inputs[0], emit),
+        (None, _) => return Err(usage("several inputs require an explicit -o/--output")),
+    };
+
+    Ok(Action::Translate(Options {
+        inputs,
+        libraries,
+        recursive,
+        output,
+        emit,
+        keep_going,
+        optimize,
+        report_statics,
+        translation,
+    }))
+}
+
+fn parse_emit(value: &str) -> Result<Emit, Error> {
+    match value {
+        "asm" => Ok(Emit::Asm),
+        "hack" => Ok(Emit::Hack),
+        other => Err(usage(&format!("--emit must be asm or hack, received {}", other))),
+    }
+}
+
+// Foo.vm becomes Foo.asm next to it, and a directory Dir becomes Dir/Dir.asm
+fn default_output(input: &Path, emit: Emit) -> PathBuf {
+    let extension = match emit {
+        Emit::Asm => "asm",
+        Emit::Hack => "hack",
+    };
+
+    if input.is_dir() {
+        let mut name = match input.canonicalize().ok().as_ref().and_then(|path| path.file_name()) {
+            Some(name) => name.to_os_string(),
+            None => input.as_os_str().to_os_string(),
+        };
+        name.push(".");
+        name.push(extension);
+
+        input.join(name)
+    } else {
+        input.with_extension(extension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Action, Error> {
+        parse_args(["vmcomp"].iter().chain(args).map(|arg| String::from(*arg)))
+    }
+
+    fn translate(args: &[&str]) -> Options {
+        match parse(args) {
+            Ok(Action::Translate(options)) => options,
+            Ok(_) => panic!("{:?} did not translate", args),
+            Err(err) => panic!("{:?}: {}", args, err),
+        }
+    }
+
+    fn usage_error(args: &[&str]) -> String {
+        match parse(args) {
+            Err(Error::Usage(message)) => String::from(message.lines().next().unwrap()),
+            _ => panic!("{:?} is not a usage error", args),
+        }
+    }
+
+    #[test]
+    fn files_are_translated_next_to_them() {
+        assert_eq!(translate(&["Foo.vm"]).output, Path::new("Foo.asm"));
+        assert_eq!(translate(&["dir/Foo.vm"]).output, Path::new("dir/Foo.asm"));
+        assert_eq!(translate(&["--emit=hack", "Foo.vm"]).output, Path::new("Foo.hack"));
+        assert!(translate(&["--emit", "hack", "Foo.vm"]).emit == Emit::Hack);
+    }
+
+    #[test]
+    fn directories_are_translated_inside_them() {
+        let dir = std::env::temp_dir().join(format!("vmcomp-cli-{}", std::process::id())).join("Dir");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut input = dir.clone().into_os_string();
+        input.push("/");
+
+        assert_eq!(translate(&[input.to_str().unwrap()]).output, dir.join("Dir.asm"));
+        assert_eq!(translate(&["--emit=hack", dir.to_str().unwrap()]).output, dir.join("Dir.hack"));
+        std::fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn several_inputs_need_an_output() {
+        assert_eq!(usage_error(&["A.vm", "B.vm"]), "several inputs require an explicit -o/--output");
+        assert_eq!(translate(&["A.vm", "-o", "Out.asm", "B.vm"]).inputs, [Path::new("A.vm"), Path::new("B.vm")]);
+    }
+
+    #[test]
+    fn options_take_inline_values() {
+        let options = translate(&["--output=Out.asm", "--sp=300", "--entry=Main.main", "-i", "Sys.vm", "--input=Main.vm"]);
+
+        assert_eq!(options.output, Path::new("Out.asm"));
+        assert_eq!(options.translation.registers.sp, 300);
+        assert_eq!(options.translation.entry, "Main.main");
+        assert_eq!(options.inputs, [Path::new("Sys.vm"), Path::new("Main.vm")]);
+
+        assert_eq!(usage_error(&["--emit=bin", "Foo.vm"]), "--emit must be asm or hack, received bin");
+        assert_eq!(usage_error(&["--sp=40000", "Foo.vm"]), "--sp must be an address between 0 and 32767, received 40000");
+        assert_eq!(usage_error(&["Foo.vm", "--output"]), "--output requires a file name");
+        assert_eq!(usage_error(&["--bogus", "Foo.vm"]), "unknown option --bogus");
+    }
+
+    #[test]
+    fn inputs_after_a_double_dash_are_files() {
+        let options = translate(&["-o", "Out.asm", "--", "--help", "-O"]);
+
+        assert_eq!(options.inputs, [Path::new("--help"), Path::new("-O")]);
+        assert!(!options.optimize);
+        assert!(matches!(parse(&["--help", "--", "Foo.vm"]), Ok(Action::Help)));
+    }
+}
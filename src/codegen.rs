@@ -0,0 +1,651 @@
+use crate::command::{ArithmeticCommand, Command, Segment};
+use crate::labels::LabelAllocator;
+use crate::options::Options;
+
+// Entry points of the routines shared by every call site when `Options::shared_calls` is set
+const CALL_ROUTINE: &str = "$$CALL";
+const RETURN_ROUTINE: &str = "$$RETURN";
+
+enum BinaryArithmeticOperator {
+    Add,
+    Sub,
+    And,
+    Or,
+}
+
+enum BooleanOperator {
+    GreaterThan,
+    LesserThan,
+    Equal,
+}
+
+// Labels are scoped to `function_name`, or to the class when outside of any function
+pub(crate) fn compile_command(
+    labels: &mut LabelAllocator,
+    command: &Command,
+    class_name: &str,
+    function_name: &str,
+    options: &Options,
+) -> Vec<String> {
+    match command {
+        Command::Push(segment, arg) => compile_push(*segment, *arg, class_name),
+        Command::Pop(segment, arg) => compile_pop(*segment, *arg, class_name),
+        Command::Arithmetic(ArithmeticCommand::Add) => compile_binary_operation(BinaryArithmeticOperator::Add),
+        Command::Arithmetic(ArithmeticCommand::Sub) => compile_binary_operation(BinaryArithmeticOperator::Sub),
+        Command::Arithmetic(ArithmeticCommand::And) => compile_binary_operation(BinaryArithmeticOperator::And),
+        Command::Arithmetic(ArithmeticCommand::Or) => compile_binary_operation(BinaryArithmeticOperator::Or),
+        Command::Arithmetic(ArithmeticCommand::Eq) => compile_boolean_operation(labels, BooleanOperator::Equal),
+        Command::Arithmetic(ArithmeticCommand::Gt) => compile_boolean_operation(labels, BooleanOperator::GreaterThan),
+        Command::Arithmetic(ArithmeticCommand::Lt) => compile_boolean_operation(labels, BooleanOperator::LesserThan),
+        Command::Arithmetic(ArithmeticCommand::Neg) => compile_neg(),
+        Command::Arithmetic(ArithmeticCommand::Not) => compile_not(),
+        Command::Label(label) => compile_label(label, function_name),
+        Command::Goto(label) => compile_goto(label, function_name),
+        Command::IfGoto(label) => compile_if_goto(label, function_name),
+        Command::Function(func_name, local_count) => compile_function(labels, func_name, *local_count),
+        Command::Call(func_name, arg_count) if options.shared_calls => compile_shared_call(labels, func_name, *arg_count),
+        Command::Call(func_name, arg_count) => compile_call(labels, func_name, *arg_count),
+        Command::Return if options.shared_calls => compile_shared_return(),
+        Command::Return => compile_return(),
+    }
+}
+
+fn segment_pointer(segment: Segment) -> &'static str {
+    match segment {
+        Segment::Local => "LCL",
+        Segment::Argument => "ARG",
+        Segment::This => "THIS",
+        Segment::That => "THAT",
+        _ => unreachable!("{} is not a pointer-based segment", segment.name()),
+    }
+}
+
+fn compile_push(segment: Segment, arg: u16, class_name: &str) -> Vec<String> {
+    let mut result = Vec::new();
+
+    match segment {
+        Segment::Local | Segment::Argument | Segment::This | Segment::That => {
+            // Store offset in D
+            result.push(format!("@{}\n", arg));
+            result.push(format!("D=A\n"));
+            // Get value (RAM[pointer + offset]) in D
+            result.push(format!("@{}\n", segment_pointer(segment)));
+            result.push(format!("A=M+D\n"));
+            result.push(format!("D=M\n"));
+        }
+        Segment::Temp => {
+            // Get value in D
+            result.push(format!("@{}\n", arg + 5));
+            result.push(format!("D=M\n"));
+        }
+        Segment::Static => {
+            // Get Class.Arg in D
+            result.push(format!("@{}.{}\n", class_name, arg));
+            result.push(format!("D=M\n"));
+        }
+        Segment::Pointer => {
+            // Get value in D
+            result.push(format!("@{}\n", if arg == 0 { "THIS" } else { "THAT" }));
+            result.push(format!("D=M\n"));
+        }
+        Segment::Constant => {
+            // Get constant in D
+            result.push(format!("@{}\n", arg));
+            result.push(format!("D=A\n"));
+        }
+    }
+
+    result.append(&mut gen_push_to_sp_and_inc());
+
+    result
+}
+
+// Instructions taken by each form of `pop local/argument/this/that <index>`
+fn unrolled_pop_cost(index: u16) -> usize {
+    8 + index as usize
+}
+
+fn offset_pop_cost() -> usize {
+    12
+}
+
+fn compile_pop(segment: Segment, arg: u16, class_name: &str) -> Vec<String> {
+    match segment {
+        Segment::Local | Segment::Argument | Segment::This | Segment::That if unrolled_pop_cost(arg) > offset_pop_cost() => {
+            compile_offset_pop(segment, arg)
+        }
+        _ => compile_unrolled_pop(segment, arg, class_name),
+    }
+}
+
+// Computes RAM[pointer] + offset in R13 first, which costs the same whatever the offset
+fn compile_offset_pop(segment: Segment, arg: u16) -> Vec<String> {
+    let mut result = Vec::new();
+
+    // Store target address (RAM[pointer + offset]) in R13
+    result.push(format!("@{}\n", arg));
+    result.push(format!("D=A\n"));
+    result.push(format!("@{}\n", segment_pointer(segment)));
+    result.push(format!("D=M+D\n"));
+    result.push(format!("@R13\n"));
+    result.push(format!("M=D\n"));
+    // Decrement stack pointer and store value in D
+    result.push(format!("@SP\n"));
+    result.push(format!("AM=M-1\n"));
+    result.push(format!("D=M\n"));
+    // Write to the target address
+    result.push(format!("@R13\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("M=D\n"));
+
+    result
+}
+
+fn compile_unrolled_pop(segment: Segment, arg: u16, class_name: &str) -> Vec<String> {
+    let mut result = Vec::new();
+
+    // Store value in D
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M-1\n"));
+    result.push(format!("D=M\n"));
+
+    match segment {
+        Segment::Local | Segment::Argument | Segment::This | Segment::That => {
+            // Point M to correct memory location
+            result.push(format!("@{}\n", segment_pointer(segment)));
+            result.push(format!("A=M\n"));
+
+            // Offset the pointer
+            (0..arg).for_each(|_| result.push(format!("A=A+1\n")));
+        }
+        Segment::Temp => {
+            // Point M to RAM[5 + arg]
+            result.push(format!("@{}\n", arg + 5));
+        }
+        Segment::Static => {
+            // Point M to Class.Arg
+            result.push(format!("@{}.{}\n", class_name, arg));
+        }
+        Segment::Pointer => {
+            // Point M to THIS or THAT
+            result.push(format!("@{}\n", if arg == 0 { "THIS" } else { "THAT" }));
+        }
+        Segment::Constant => unreachable!("pop constant is rejected by the parser"),
+    }
+
+    // Write to memory location
+    result.push(format!("M=D\n"));
+    // Decrement stack pointer
+    result.push(format!("@SP\n"));
+    result.push(format!("M=M-1\n"));
+
+    result
+}
+
+fn compile_neg() -> Vec<String> {
+    let mut result = Vec::new();
+    // Get value in D
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M-1\n"));
+    result.push(format!("D=M\n"));
+    // D = 0 - D (2's complement)
+    result.push(format!("@0\n"));
+    result.push(format!("D=A-D\n"));
+    // Store the result
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M-1\n"));
+    result.push(format!("M=D\n"));
+
+    result
+}
+
+fn compile_not() -> Vec<String> {
+    let mut result = Vec::new();
+    // Point A to value
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M-1\n"));
+    // Value = not value
+    result.push(format!("M=!M\n"));
+
+    result
+}
+
+fn compile_label(label: &str, function_name: &str) -> Vec<String> {
+    vec![format!("({}${})\n", function_name, label)]
+}
+
+fn compile_goto(label: &str, function_name: &str) -> Vec<String> {
+    let mut result = Vec::new();
+
+    result.push(format!("@{}${}\n", function_name, label));
+    // Unconditional jump
+    result.push(format!("0;JMP\n"));
+
+    result
+}
+
+fn compile_if_goto(label: &str, function_name: &str) -> Vec<String> {
+    let mut result = Vec::new();
+
+    // Get the value on top of the stack in D
+    result.push(format!("@SP\n"));
+    result.push(format!("M=M-1\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("D=M\n"));
+    // Jump to label if the value is not 0
+    result.push(format!("@{}${}\n", function_name, label));
+    result.push(format!("D;JNE\n"));
+
+    result
+}
+
+fn compile_call(labels: &mut LabelAllocator, func_name: &str, param_count: u16) -> Vec<String> {
+    let return_address = labels.unique(func_name, "ret");
+
+    let mut result = Vec::new();
+
+    result.push(format!("@{}\n", return_address));
+    result.push(format!("D=A\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("M=D\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("M=M+1\n"));
+
+    result.push(format!("@LCL\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("M=D\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("M=M+1\n"));
+
+    result.push(format!("@ARG\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("M=D\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("M=M+1\n"));
+
+    result.push(format!("@THIS\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("M=D\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("M=M+1\n"));
+
+    result.push(format!("@THAT\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("M=D\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("M=M+1\n"));
+
+    result.push(format!("@SP\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@5\n"));
+    result.push(format!("D=D-A\n"));
+    result.push(format!("@{}\n", param_count));
+    result.push(format!("D=D-A\n"));
+    result.push(format!("@ARG\n"));
+    result.push(format!("M=D\n"));
+    
+    result.push(format!("@SP\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@LCL\n"));
+    result.push(format!("M=D\n"));
+
+    result.push(format!("@{}\n", func_name));
+    result.push(format!("0;JMP\n"));
+    result.push(format!("({})\n", return_address));
+
+    result
+}
+
+fn compile_shared_call(labels: &mut LabelAllocator, func_name: &str, param_count: u16) -> Vec<String> {
+    let return_address = labels.unique(func_name, "ret");
+
+    let mut result = Vec::new();
+
+    // Callee in R13
+    result.push(format!("@{}\n", func_name));
+    result.push(format!("D=A\n"));
+    result.push(format!("@R13\n"));
+    result.push(format!("M=D\n"));
+    // Argument count in R14
+    result.push(format!("@{}\n", param_count));
+    result.push(format!("D=A\n"));
+    result.push(format!("@R14\n"));
+    result.push(format!("M=D\n"));
+    // Return address in R15
+    result.push(format!("@{}\n", return_address));
+    result.push(format!("D=A\n"));
+    result.push(format!("@R15\n"));
+    result.push(format!("M=D\n"));
+
+    result.push(format!("@{}\n", CALL_ROUTINE));
+    result.push(format!("0;JMP\n"));
+    result.push(format!("({})\n", return_address));
+
+    result
+}
+
+fn compile_shared_return() -> Vec<String> {
+    vec![format!("@{}\n", RETURN_ROUTINE), format!("0;JMP\n")]
+}
+
+fn compile_function(labels: &mut LabelAllocator, class_name: &str, local_count: u16) -> Vec<String> {
+    let loop_label = labels.unique(class_name, "loop");
+    let end_label = labels.unique(class_name, "end");
+
+    let mut result = Vec::new();
+
+    // Define a label for the function
+    result.push(format!("({})\n", class_name));
+    // Store local variables count in D
+    result.push(format!("@{}\n", local_count));
+    result.push(format!("D=A\n"));
+    // Set D values to 0 
+    result.push(format!("@{}\n", end_label));
+    result.push(format!("D;JEQ\n"));
+    result.push(format!("({})\n", loop_label));
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("M=0\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("M=M+1\n"));
+    result.push(format!("@{}\n", loop_label));
+    result.push(format!("D=D-1;JNE\n"));
+    result.push(format!("({})\n", end_label));
+    
+    result
+}
+
+fn compile_return() -> Vec<String> {
+    let mut result = Vec::new();
+
+    result.push(format!("@LCL\n"));
+    result.push(format!("D=M\n"));
+
+    result.push(format!("@5\n"));
+    result.push(format!("A=D-A\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@13\n"));
+    result.push(format!("M=D\n"));
+
+    result.push(format!("@SP\n"));
+    result.push(format!("M=M-1\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@ARG\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("M=D\n"));
+
+    result.push(format!("@ARG\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("M=D+1\n"));
+
+    result.push(format!("@LCL\n"));
+    result.push(format!("M=M-1\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@THAT\n"));
+    result.push(format!("M=D\n"));
+
+    result.push(format!("@LCL\n"));
+    result.push(format!("M=M-1\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@THIS\n"));
+    result.push(format!("M=D\n"));
+
+    result.push(format!("@LCL\n"));
+    result.push(format!("M=M-1\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@ARG\n"));
+    result.push(format!("M=D\n"));
+
+    result.push(format!("@LCL\n"));
+    result.push(format!("M=M-1\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@LCL\n"));
+    result.push(format!("M=D\n"));
+
+    result.push(format!("@13\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("0;JMP\n"));
+
+    result
+}
+
+fn compile_binary_operation(operator: BinaryArithmeticOperator) -> Vec<String> {
+    let op = match operator {
+        BinaryArithmeticOperator::Add => "+",
+        BinaryArithmeticOperator::Sub => "-",
+        BinaryArithmeticOperator::And => "&",
+        BinaryArithmeticOperator::Or => "|",
+    };
+
+    let mut result = Vec::new();
+
+    // Get y in D
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M-1\n"));
+    result.push(format!("D=M\n"));
+    // Point M to x
+    result.push(format!("A=A-1\n"));
+    // Perform operation and store the result (x op y)
+    result.push(format!("M=M{}D\n", op));
+    // Decrement stack pointer
+    result.push(format!("@SP\n"));
+    result.push(format!("M=M-1\n"));
+
+    result
+}
+
+fn compile_boolean_operation(labels: &mut LabelAllocator, operator: BooleanOperator) -> Vec<String> {
+    let op = match operator {
+        BooleanOperator::GreaterThan => "JGT",
+        BooleanOperator::LesserThan => "JLT",
+        BooleanOperator::Equal => "JEQ",
+    };
+
+    let true_label = labels.unique("", "TRUE");
+    let else_label = labels.unique("", "ELSE");
+
+    let mut result = match operator {
+        BooleanOperator::Equal => gen_difference(),
+        _ => gen_signed_difference(labels),
+    };
+
+    result.push(format!("@{}\n", true_label));
+    // Jump to TRUE if x op y is true
+    result.push(format!("D;{}\n", op));
+    // Set result (D) to zero (false)
+    result.push(format!("D=0\n"));
+    result.push(format!("@{}\n", else_label));
+    result.push(format!("0;JMP\n"));
+    result.push(format!("({})\n", true_label));
+    // Set result (D) to minus one (true)
+    result.push(format!("D=-1\n"));
+    result.push(format!("({})\n", else_label));
+    // Save result in SP - 2 (overrides the first operand in the stack)
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M-1\n"));
+    result.push(format!("A=A-1\n"));
+    result.push(format!("M=D\n"));
+    // Decrement stack pointer
+    result.push(format!("@SP\n"));
+    result.push(format!("M=M-1\n"));
+    
+    result
+}
+
+// D = x - y, with x and y the two values on top of the stack
+fn gen_difference() -> Vec<String> {
+    let mut result = Vec::new();
+
+    // Get y in D
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M-1\n"));
+    result.push(format!("D=M\n"));
+    // M now points to x
+    result.push(format!("A=A-1\n"));
+    // Store diff in D (D = x - y)
+    result.push(format!("D=M-D\n"));
+
+    result
+}
+
+// Like `gen_difference`, but only keeps the sign of x - y when x and y have different signs,
+// as the subtraction overflows for e.g. 20000 - (-20000)
+fn gen_signed_difference(labels: &mut LabelAllocator) -> Vec<String> {
+    let y_negative_label = labels.unique("", "YNEG");
+    let same_sign_label = labels.unique("", "SAME");
+    let compare_label = labels.unique("", "CMP");
+
+    let mut result = Vec::new();
+
+    // Get y in D
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M-1\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@{}\n", y_negative_label));
+    result.push(format!("D;JLT\n"));
+    // y >= 0: get x in D
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M-1\n"));
+    result.push(format!("A=A-1\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@{}\n", same_sign_label));
+    result.push(format!("D;JGE\n"));
+    // x < 0 <= y, so x < y
+    result.push(format!("D=-1\n"));
+    result.push(format!("@{}\n", compare_label));
+    result.push(format!("0;JMP\n"));
+    result.push(format!("({})\n", y_negative_label));
+    // y < 0: get x in D
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M-1\n"));
+    result.push(format!("A=A-1\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@{}\n", same_sign_label));
+    result.push(format!("D;JLT\n"));
+    // y < 0 <= x, so x > y
+    result.push(format!("D=1\n"));
+    result.push(format!("@{}\n", compare_label));
+    result.push(format!("0;JMP\n"));
+    // Same sign: x - y can't overflow
+    result.push(format!("({})\n", same_sign_label));
+    result.append(&mut gen_difference());
+    result.push(format!("({})\n", compare_label));
+
+    result
+}
+
+fn gen_push_to_sp_and_inc() -> Vec<String> {
+    let mut result = Vec::new();
+
+    // Write to stack pointer
+    result.push(format!("@SP\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("M=D\n"));
+    // Increment stack pointer
+    result.push(format!("@SP\n"));
+    result.push(format!("M=M+1\n"));
+
+    result
+}
+
+pub(crate) fn gen_init_code(labels: &mut LabelAllocator, options: &Options) -> Vec<String> {
+    let registers = &options.registers;
+    let mut result = Vec::new();
+
+    result.push(format!("// Initialisation code\n"));
+
+    // Set SP
+    result.push(format!("@{}\n", registers.sp));
+    result.push(format!("D=A\n"));
+    result.push(format!("@SP\n"));
+    result.push(format!("M=D\n"));
+    // Set LCL
+    result.push(format!("@{}\n", registers.lcl));
+    result.push(format!("D=A\n"));
+    result.push(format!("@LCL\n"));
+    result.push(format!("M=D\n"));
+    // Set ARG
+    result.push(format!("@{}\n", registers.arg));
+    result.push(format!("D=A\n"));
+    result.push(format!("@ARG\n"));
+    result.push(format!("M=D\n"));
+    // Set THIS
+    result.push(format!("@{}\n", registers.this));
+    result.push(format!("D=A\n"));
+    result.push(format!("@THIS\n"));
+    result.push(format!("M=D\n"));
+    // Set THAT
+    result.push(format!("@{}\n", registers.that));
+    result.push(format!("D=A\n"));
+    result.push(format!("@THAT\n"));
+    result.push(format!("M=D\n"));
+
+    let call = Command::Call(options.entry.clone(), 0);
+    result.append(&mut compile_command(labels, &call, "Sys", "Sys", options));
+
+    result.push(format!("\n"));
+
+    result
+}
+
+// `$$CALL` and `$$RETURN`, behind a jump so that the code before them doesn't run into them
+pub(crate) fn gen_shared_routines(labels: &mut LabelAllocator) -> Vec<String> {
+    let start_label = labels.unique("", "START");
+
+    let mut result = Vec::new();
+
+    result.push(format!("// Shared call and return routines\n"));
+    result.push(format!("@{}\n", start_label));
+    result.push(format!("0;JMP\n"));
+
+    // Expects the callee in R13, the argument count in R14 and the return address in R15
+    result.push(format!("({})\n", CALL_ROUTINE));
+
+    for register in ["R15", "LCL", "ARG", "THIS", "THAT"].iter() {
+        result.push(format!("@{}\n", register));
+        result.push(format!("D=M\n"));
+        result.append(&mut gen_push_to_sp_and_inc());
+    }
+
+    // ARG = SP - 5 - argument count
+    result.push(format!("@SP\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@5\n"));
+    result.push(format!("D=D-A\n"));
+    result.push(format!("@R14\n"));
+    result.push(format!("D=D-M\n"));
+    result.push(format!("@ARG\n"));
+    result.push(format!("M=D\n"));
+    // LCL = SP
+    result.push(format!("@SP\n"));
+    result.push(format!("D=M\n"));
+    result.push(format!("@LCL\n"));
+    result.push(format!("M=D\n"));
+    // Jump to the callee
+    result.push(format!("@R13\n"));
+    result.push(format!("A=M\n"));
+    result.push(format!("0;JMP\n"));
+
+    result.push(format!("({})\n", RETURN_ROUTINE));
+    result.append(&mut compile_return());
+
+    result.push(format!("({})\n", start_label));
+    result.push(format!("\n"));
+
+    result
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Local,
+    Argument,
+    This,
+    That,
+    Temp,
+    Static,
+    Pointer,
+    Constant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithmeticCommand {
+    Add,
+    Sub,
+    Neg,
+    Eq,
+    Gt,
+    Lt,
+    And,
+    Or,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Push(Segment, u16),
+    Pop(Segment, u16),
+    Arithmetic(ArithmeticCommand),
+    Label(String),
+    Goto(String),
+    IfGoto(String),
+    Function(String, u16),
+    Call(String, u16),
+    Return,
+}
+
+impl Segment {
+    pub fn from_name(name: &str) -> Option<Segment> {
+        match name {
+            "local" => Some(Segment::Local),
+            "argument" => Some(Segment::Argument),
+            "this" => Some(Segment::This),
+            "that" => Some(Segment::That),
+            "temp" => Some(Segment::Temp),
+            "static" => Some(Segment::Static),
+            "pointer" => Some(Segment::Pointer),
+            "constant" => Some(Segment::Constant),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Segment::Local => "local",
+            Segment::Argument => "argument",
+            Segment::This => "this",
+            Segment::That => "that",
+            Segment::Temp => "temp",
+            Segment::Static => "static",
+            Segment::Pointer => "pointer",
+            Segment::Constant => "constant",
+        }
+    }
+}
+
+impl ArithmeticCommand {
+    pub fn from_name(name: &str) -> Option<ArithmeticCommand> {
+        match name {
+            "add" => Some(ArithmeticCommand::Add),
+            "sub" => Some(ArithmeticCommand::Sub),
+            "neg" => Some(ArithmeticCommand::Neg),
+            "eq" => Some(ArithmeticCommand::Eq),
+            "gt" => Some(ArithmeticCommand::Gt),
+            "lt" => Some(ArithmeticCommand::Lt),
+            "and" => Some(ArithmeticCommand::And),
+            "or" => Some(ArithmeticCommand::Or),
+            "not" => Some(ArithmeticCommand::Not),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ArithmeticCommand::Add => "add",
+            ArithmeticCommand::Sub => "sub",
+            ArithmeticCommand::Neg => "neg",
+            ArithmeticCommand::Eq => "eq",
+            ArithmeticCommand::Gt => "gt",
+            ArithmeticCommand::Lt => "lt",
+            ArithmeticCommand::And => "and",
+            ArithmeticCommand::Or => "or",
+            ArithmeticCommand::Not => "not",
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Push(segment, index) => write!(f, "push {} {}", segment.name(), index),
+            Command::Pop(segment, index) => write!(f, "pop {} {}", segment.name(), index),
+            Command::Arithmetic(command) => write!(f, "{}", command.name()),
+            Command::Label(label) => write!(f, "label {}", label),
+            Command::Goto(label) => write!(f, "goto {}", label),
+            Command::IfGoto(label) => write!(f, "if-goto {}", label),
+            Command::Function(name, local_count) => write!(f, "function {} {}", name, local_count),
+            Command::Call(name, arg_count) => write!(f, "call {} {}", name, arg_count),
+            Command::Return => write!(f, "return"),
+        }
+    }
+}
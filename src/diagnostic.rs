@@ -0,0 +1,111 @@
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Where a diagnostic points to: a 1-based line and a 0-based column range within it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub columns: Range<usize>,
+    pub snippet: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub location: Option<Location>,
+    pub help: Option<String>,
+}
+
+impl Location {
+    pub fn new(file: &str, line: usize, columns: Range<usize>, snippet: &str) -> Location {
+        Location {
+            file: String::from(file),
+            line,
+            columns,
+            snippet: String::from(snippet),
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: String, location: Option<Location>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message,
+            location,
+            help: None,
+        }
+    }
+
+    pub fn warning(code: &'static str, message: String, location: Option<Location>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            code,
+            message,
+            location,
+            help: None,
+        }
+    }
+
+    pub fn with_help(mut self, help: String) -> Diagnostic {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}[{}]: {}", self.severity, self.code, self.message)?;
+
+        let gutter = match &self.location {
+            Some(location) => location.line.to_string().len(),
+            None => 0,
+        };
+        let pad = " ".repeat(gutter);
+
+        if let Some(location) = &self.location {
+            writeln!(f, "{}--> {}:{}:{}", pad, location.file, location.line, location.columns.start + 1)?;
+            writeln!(f, "{} |", pad)?;
+            writeln!(f, "{} | {}", location.line, location.snippet)?;
+
+            // Keep tabs so the carets line up with the snippet above
+            let start = location.columns.start.min(location.snippet.len());
+            let end = location.columns.end.clamp(start, location.snippet.len());
+            let indent: String = location.snippet[..start].chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let carets = "^".repeat(location.snippet[start..end].chars().count().max(1));
+
+            writeln!(f, "{} | {}{}", pad, indent, carets)?;
+        }
+
+        if let Some(help) = &self.help {
+            writeln!(f, "{} = help: {}", pad, help)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::emulator::{Cpu, Stop};
+use crate::interpreter::Vm;
+use crate::options::Bootstrap;
+use crate::{Diagnostics, Options, SourceFile};
+
+const TEMP: Range<u16> = 5..13;
+const STATIC_BASE: u16 = 16;
+
+// Saved return addresses differ between the two: VM command indices against ROM addresses
+const MASKED: u16 = 0xDEAD;
+
+/// How to run a program through both the VM interpreter and translate+emulate.
+#[derive(Debug, Clone)]
+pub struct Harness {
+    pub options: Options,
+    /// Whether to run the translated program through the peephole optimizer.
+    pub optimize: bool,
+    /// RAM contents to set before running, e.g. `(0, 256)` for the stack pointer.
+    pub ram: Vec<(u16, u16)>,
+    /// Extra RAM ranges to compare, e.g. where `this` and `that` point.
+    pub watch: Vec<Range<u16>>,
+    pub max_steps: u64,
+}
+
+/// The memory a VM program can observe once it stops.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub pointers: [u16; 5],
+    pub stack: Vec<u16>,
+    pub temp: Vec<u16>,
+    pub statics: Vec<u16>,
+    pub watched: Vec<Vec<u16>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Failure {
+    Compile(Diagnostics),
+    Runtime(String),
+    /// The VM interpreter did not halt within `max_steps`.
+    NoHalt,
+    /// The translated program did not halt, while the interpreter did.
+    NoHaltTranslated,
+    Divergence { expected: Box<Snapshot>, actual: Box<Snapshot> },
+}
+
+impl Default for Harness {
+    // Project 7 style: no bootstrap, the segments set by hand
+    fn default() -> Harness {
+        Harness {
+            options: Options {
+                bootstrap: Bootstrap::Never,
+                ..Options::default()
+            },
+            optimize: false,
+            ram: vec![(0, 256), (1, 300), (2, 400), (3, 3000), (4, 3010)],
+            watch: vec![Range { start: 3000, end: 3020 }],
+            max_steps: 100_000,
+        }
+    }
+}
+
+/// Runs `files` through the interpreter and through the translator and emulator,
+/// and compares what both leave in memory.
+pub fn check(files: &[SourceFile], harness: &Harness) -> Result<(), Failure> {
+    let mut asm = crate::compile_program(files, &harness.options).map_err(Failure::Compile)?;
+    if harness.optimize {
+        asm = crate::optimizer::optimize(&asm);
+    };
+
+    let mut vm = Vm::load(files, &harness.options).map_err(Failure::Compile)?;
+    let mut cpu = Cpu::from_asm("output.asm", &asm.to_string()).map_err(Failure::Compile)?;
+
+    for (address, value) in harness.ram.iter() {
+        vm.set_ram(*address, *value);
+        cpu.set_ram(*address, *value);
+    }
+
+    let stack_base = vm.ram(0);
+
+    match vm.run(harness.max_steps).map_err(Failure::Runtime)? {
+        Stop::Halted => (),
+        _ => return Err(Failure::NoHalt),
+    };
+
+    // Commands other than `function` run a bounded number of instructions, a few dozen at most
+    // for call and return, while `function` loops 7 instructions per local variable
+    let max_cycles = vm.steps * 600 + vm.locals * 7 + 1000;
+
+    match cpu.run(max_cycles) {
+        Stop::Halted => (),
+        _ => return Err(Failure::NoHaltTranslated),
+    };
+
+    let expected = snapshot(|address| vm.ram(address), stack_base, vm.static_count(), &harness.watch);
+    let actual = snapshot(|address| cpu.ram(address), stack_base, vm.static_count(), &harness.watch);
+
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(Failure::Divergence {
+            expected: Box::new(expected),
+            actual: Box::new(actual),
+        })
+    }
+}
+
+/// Removes as many lines as possible from `files` while `failing` still holds.
+pub fn shrink<F>(files: &[SourceFile], failing: F) -> Vec<SourceFile>
+where
+    F: Fn(&[SourceFile]) -> bool,
+{
+    let mut files: Vec<SourceFile> = files.to_vec();
+
+    for index in 0..files.len() {
+        let mut lines: Vec<String> = files[index].source.lines().map(String::from).collect();
+        let mut chunk = lines.len().max(1);
+
+        // Try removing ever smaller chunks of lines until single lines can't go either
+        while chunk > 0 {
+            let mut start = 0;
+
+            while start < lines.len() {
+                let mut candidate = lines.clone();
+                candidate.drain(start..(start + chunk).min(lines.len()));
+
+                let mut candidate_files = files.clone();
+                candidate_files[index].source = candidate.join("\n");
+
+                if failing(&candidate_files) {
+                    lines = candidate;
+                    files = candidate_files;
+                } else {
+                    start += chunk;
+                };
+            }
+
+            chunk /= 2;
+        }
+    }
+
+    files
+}
+
+/// Checks `files`, and on a divergence shrinks them to the smallest program that still diverges.
+pub fn check_and_shrink(files: &[SourceFile], harness: &Harness) -> Result<(), (Failure, Vec<SourceFile>)> {
+    match check(files, harness) {
+        Err(failure @ Failure::Divergence { .. }) => {
+            let diverges = |files: &[SourceFile]| matches!(check(files, harness), Err(Failure::Divergence { .. }));
+            Err((failure, shrink(files, diverges)))
+        }
+        Err(failure) => Err((failure, files.to_vec())),
+        Ok(()) => Ok(()),
+    }
+}
+
+fn snapshot<F: Fn(u16) -> u16>(ram: F, stack_base: u16, static_count: usize, watch: &[Range<u16>]) -> Snapshot {
+    let pointers = [ram(0), ram(1), ram(2), ram(3), ram(4)];
+    let mut stack: Vec<u16> = (stack_base..pointers[0].max(stack_base)).map(&ram).collect();
+
+    // Walk the frames still on the stack, masking the return address of each
+    let mut frame = pointers[1];
+    while frame >= stack_base + 5 && frame <= pointers[0] {
+        stack[(frame - 5 - stack_base) as usize] = MASKED;
+
+        let caller = ram(frame - 4);
+        if caller >= frame {
+            break;
+        };
+        frame = caller;
+    }
+
+    Snapshot {
+        pointers,
+        stack,
+        temp: TEMP.map(&ram).collect(),
+        statics: (STATIC_BASE..STATIC_BASE + static_count as u16).map(&ram).collect(),
+        watched: watch.iter().map(|range| range.clone().map(&ram).collect()).collect(),
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let signed = |values: &[u16]| values.iter().map(|value| *value as i16).collect::<Vec<i16>>();
+
+        writeln!(f, "SP={} LCL={} ARG={} THIS={} THAT={}",
+            self.pointers[0], self.pointers[1], self.pointers[2], self.pointers[3], self.pointers[4])?;
+        writeln!(f, "stack:   {:?}", signed(&self.stack))?;
+        writeln!(f, "temp:    {:?}", signed(&self.temp))?;
+        writeln!(f, "statics: {:?}", signed(&self.statics))?;
+        self.watched.iter().try_for_each(|values| writeln!(f, "watched: {:?}", signed(values)))
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::Compile(diagnostics) => diagnostics.iter().try_for_each(|diagnostic| write!(f, "{}", diagnostic)),
+            Failure::Runtime(message) => write!(f, "runtime error: {}", message),
+            Failure::NoHalt => write!(f, "the VM program does not halt"),
+            Failure::NoHaltTranslated => write!(f, "the translated program does not halt"),
+            Failure::Divergence { expected, actual } => {
+                write!(f, "interpreter:\n{}translated:\n{}", expected, actual)
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+
+use crate::assembler;
+use crate::Diagnostics;
+
+pub const RAM_SIZE: usize = 32768;
+pub const ROM_SIZE: usize = 32768;
+
+/// Why `Cpu::run` handed control back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The cycle budget given to `run` is spent.
+    CycleLimit,
+    /// The program counter reached a breakpoint, before executing it.
+    Breakpoint(u16),
+    /// The program jumped onto itself, or past the end of the ROM contents.
+    Halted,
+}
+
+/// The Hack CPU with its instruction and data memories. Every instruction takes one cycle.
+pub struct Cpu {
+    pub a: u16,
+    pub d: u16,
+    pub pc: u16,
+    pub cycles: u64,
+    ram: Vec<u16>,
+    rom: Vec<u16>,
+    program_size: usize,
+    breakpoints: HashSet<u16>,
+    // Set when `run` stopped on a breakpoint, so the next `run` steps past it
+    on_breakpoint: bool,
+}
+
+impl Cpu {
+    pub fn new(program: &[u16]) -> Cpu {
+        let mut rom = vec![0; ROM_SIZE];
+        let program_size = program.len().min(ROM_SIZE);
+        rom[..program_size].copy_from_slice(&program[..program_size]);
+
+        Cpu {
+            a: 0,
+            d: 0,
+            pc: 0,
+            cycles: 0,
+            ram: vec![0; RAM_SIZE],
+            rom,
+            program_size,
+            breakpoints: HashSet::new(),
+            on_breakpoint: false,
+        }
+    }
+
+    pub fn from_asm(file_name: &str, source: &str) -> Result<Cpu, Diagnostics> {
+        Ok(Cpu::new(&assembler::assemble(file_name, source)?))
+    }
+
+    pub fn from_hack(file_name: &str, source: &str) -> Result<Cpu, Diagnostics> {
+        Ok(Cpu::new(&assembler::read_hack(file_name, source)?))
+    }
+
+    pub fn ram(&self, address: u16) -> u16 {
+        self.ram[address as usize % RAM_SIZE]
+    }
+
+    pub fn set_ram(&mut self, address: u16, value: u16) {
+        self.ram[address as usize % RAM_SIZE] = value;
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.breakpoints.remove(&address);
+    }
+
+    /// Clears the registers and restarts the program, keeping RAM as it is.
+    pub fn reset(&mut self) {
+        self.a = 0;
+        self.d = 0;
+        self.pc = 0;
+        self.cycles = 0;
+        self.on_breakpoint = false;
+    }
+
+    /// Runs until `max_cycles` more instructions have executed, a breakpoint is hit or the program halts.
+    /// After stopping on a breakpoint, the next `run` executes it rather than stopping there again.
+    pub fn run(&mut self, max_cycles: u64) -> Stop {
+        let limit = self.cycles + max_cycles;
+        let mut resuming = std::mem::take(&mut self.on_breakpoint);
+
+        loop {
+            if self.pc as usize >= self.program_size || self.is_halted() {
+                return Stop::Halted;
+            };
+
+            if !resuming && self.breakpoints.contains(&self.pc) {
+                self.on_breakpoint = true;
+                return Stop::Breakpoint(self.pc);
+            };
+
+            if self.cycles >= limit {
+                return Stop::CycleLimit;
+            };
+
+            self.step();
+            resuming = false;
+        }
+    }
+
+    /// Executes the instruction at PC.
+    pub fn step(&mut self) {
+        let instruction = self.rom[self.pc as usize % ROM_SIZE];
+        self.cycles += 1;
+
+        // A-instruction
+        if instruction & 0x8000 == 0 {
+            self.a = instruction;
+            self.pc = self.pc.wrapping_add(1);
+            return;
+        };
+
+        // C-instruction: 111a cccc ccdd djjj, reading and writing M at the current A
+        let address = self.a;
+        let y = if instruction & 0x1000 != 0 { self.ram(address) } else { self.a };
+        let out = alu(self.d, y, (instruction >> 6) & 0b111111);
+
+        if instruction & 0b001000 != 0 {
+            self.set_ram(address, out);
+        };
+        if instruction & 0b010000 != 0 {
+            self.d = out;
+        };
+        if instruction & 0b100000 != 0 {
+            self.a = out;
+        };
+
+        let negative = out & 0x8000 != 0;
+        let zero = out == 0;
+        let jump = (instruction & 0b100 != 0 && negative)
+            || (instruction & 0b010 != 0 && zero)
+            || (instruction & 0b001 != 0 && !negative && !zero);
+
+        self.pc = if jump { address } else { self.pc.wrapping_add(1) };
+    }
+
+    // `(END) @END 0;JMP`, the usual way for Hack programs to stop
+    fn is_halted(&self) -> bool {
+        let pc = self.pc as usize;
+
+        pc + 1 < self.program_size
+            && self.rom[pc] == pc as u16
+            && self.rom[pc + 1] == 0b1110_1010_1000_0111
+    }
+}
+
+// The Hack ALU, driven by its six control bits zx nx zy ny f no
+fn alu(x: u16, y: u16, control: u16) -> u16 {
+    let x = if control & 0b100000 != 0 { 0 } else { x };
+    let x = if control & 0b010000 != 0 { !x } else { x };
+    let y = if control & 0b001000 != 0 { 0 } else { y };
+    let y = if control & 0b000100 != 0 { !y } else { y };
+    let out = if control & 0b000010 != 0 { x.wrapping_add(y) } else { x & y };
+
+    if control & 0b000001 != 0 { !out } else { out }
+}
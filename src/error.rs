@@ -0,0 +1,56 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum Error {
+    Usage(String),
+    Io { path: PathBuf, source: io::Error },
+    Translation { target: String, error_count: usize },
+    Test { failed: usize, total: usize },
+    Runtime(String),
+}
+
+impl Error {
+    pub fn io(path: &Path, source: io::Error) -> Error {
+        Error::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Translation { .. } | Error::Test { .. } => 1,
+            Error::Usage(_) => 2,
+            Error::Io { .. } => 3,
+            Error::Runtime(_) => 4,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Usage(message) => write!(f, "{}", message),
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Translation { target, error_count } => write!(
+                f,
+                "could not translate {} due to {} previous error(s)",
+                target, error_count
+            ),
+            Error::Test { failed, total } => write!(f, "{} of {} test script(s) failed", failed, total),
+            Error::Runtime(message) => write!(f, "runtime error: {}", message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::SourceFile;
+
+pub fn read_file(file_path: &Path) -> Result<SourceFile, Error> {
+    let source = fs::read_to_string(file_path).map_err(|err| Error::io(file_path, err))?;
+    let file_name = file_path.file_name().unwrap().to_string_lossy().into_owned();
+
+    Ok(SourceFile::new(&file_name, &source))
+}
+
+// Writes next to the destination then renames, so a failed run never leaves a truncated file
+pub fn write_lines(file_path: &Path, lines: &[String]) -> Result<(), Error> {
+    let mut temp_name = file_path.as_os_str().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = Path::new(&temp_name);
+
+    let file = File::create(temp_path).map_err(|err| Error::io(temp_path, err))?;
+    let mut writer = io::BufWriter::new(file);
+
+    for line in lines {
+        writer.write_all(line.as_bytes()).map_err(|err| Error::io(temp_path, err))?;
+    };
+
+    writer.flush().map_err(|err| Error::io(temp_path, err))?;
+
+    fs::rename(temp_path, file_path).map_err(|err| Error::io(file_path, err))
+}
+
+// Sorted by name, as `fs::read_dir` order changes from one machine to the next
+pub fn read_dir(dir_path: &Path) -> Result<Vec<SourceFile>, Error> {
+    vm_files(dir_path, false)?.iter().map(|path| read_file(path)).collect()
+}
+
+pub fn read_input(input: &Path) -> Result<Vec<SourceFile>, Error> {
+    input_files(input, false)?.iter().map(|path| read_file(path)).collect()
+}
+
+/// Reads every input in order, each file once: `-i Sys.vm -i .` links Sys.vm first, then the
+/// rest of the directory. With `recursive`, directories are searched down to their subdirectories.
+pub fn read_inputs(inputs: &[PathBuf], recursive: bool) -> Result<Vec<SourceFile>, Error> {
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+
+    for input in inputs {
+        for path in input_files(input, recursive)? {
+            let canonical = path.canonicalize().map_err(|err| Error::io(&path, err))?;
+
+            if seen.insert(canonical) {
+                files.push(read_file(&path)?);
+            };
+        }
+    }
+
+    Ok(files)
+}
+
+/// Reads the library files no source is named after, so that a program can bring its own Math.vm.
+pub fn read_libraries(libraries: &[PathBuf], recursive: bool, sources: &[SourceFile]) -> Result<Vec<SourceFile>, Error> {
+    let files = read_inputs(libraries, recursive)?;
+
+    Ok(files.into_iter().filter(|file| sources.iter().all(|source| source.name != file.name)).collect())
+}
+
+fn vm_files(dir_path: &Path, recursive: bool) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = Vec::new();
+    let mut dirs = vec![dir_path.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let read_dir = fs::read_dir(&dir).map_err(|err| Error::io(&dir, err))?;
+
+        for entry in read_dir {
+            let entry = entry.map_err(|err| Error::io(&dir, err))?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let file_type = entry.file_type().map_err(|err| Error::io(&entry.path(), err))?;
+
+            if file_type.is_dir() && recursive {
+                dirs.push(entry.path());
+            } else if !file_type.is_dir() && file_name.ends_with(".vm") {
+                paths.push(entry.path());
+            };
+        };
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+fn input_files(input: &Path, recursive: bool) -> Result<Vec<PathBuf>, Error> {
+    let metadata = fs::metadata(input).map_err(|err| Error::io(input, err))?;
+
+    if metadata.is_dir() {
+        vm_files(input, recursive)
+    } else if input.extension().is_some_and(|extension| extension == "vm") {
+        Ok(vec![input.to_path_buf()])
+    } else {
+        Err(Error::Usage(format!("{} is neither a .vm file nor a directory", input.display())))
+    }
+}
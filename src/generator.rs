@@ -0,0 +1,293 @@
+use crate::command::{ArithmeticCommand, Command, Segment};
+use crate::SourceFile;
+
+// Where the differential harness points `this` and `that`: keeps every access inside its watched range
+const POINTER_BASE: u16 = 3000;
+const SEGMENT_SIZE: u16 = 10;
+
+const MAX_EXPRESSION_DEPTH: u32 = 3;
+const MAX_BLOCK_DEPTH: u32 = 2;
+
+/// A small xorshift generator, so that a failing seed always reproduces the same program.
+pub struct Rng {
+    state: u64,
+}
+
+/// A random program along with what running it must leave behind.
+pub struct Program {
+    pub files: Vec<SourceFile>,
+    /// Values left on the stack by the top level code once it halts.
+    pub stack_depth: u16,
+}
+
+struct Function {
+    name: String,
+    arg_count: u16,
+    local_count: u16,
+}
+
+// Where the code being generated lives
+#[derive(Clone, Copy)]
+struct Scope {
+    // Index in `Generator::functions`, or `None` for the top level code
+    function: Option<usize>,
+    in_loop: bool,
+}
+
+/// Generates well-formed VM programs: every command finds enough values on the stack,
+/// every index is in bounds, every jump target exists, and functions only call functions
+/// defined after them so that every program halts.
+pub struct Generator {
+    rng: Rng,
+    functions: Vec<Function>,
+    lines: Vec<String>,
+    label_count: usize,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // Zero is the one state xorshift never leaves
+        Rng { state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1 }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A number in `0..bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound.max(1)
+    }
+
+    pub fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+}
+
+/// Generates the program for `seed`.
+pub fn generate(seed: u64) -> Program {
+    Generator::new(seed).generate()
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Generator {
+        Generator {
+            rng: Rng::new(seed),
+            functions: Vec::new(),
+            lines: Vec::new(),
+            label_count: 0,
+        }
+    }
+
+    pub fn generate(mut self) -> Program {
+        let class_names = ["Main", "Util"];
+        let function_count = 1 + self.rng.below(4) as usize;
+
+        // Local 0 is kept for loop counters
+        self.functions = (0..function_count).map(|index| Function {
+            name: format!("{}.f{}", self.rng.pick(&class_names), index),
+            arg_count: self.rng.below(4) as u16,
+            local_count: 1 + self.rng.below(4) as u16,
+        }).collect();
+
+        // Top level code first, as there is no bootstrap to jump over the functions
+        let scope = Scope { function: None, in_loop: false };
+        let stack_depth = 1 + self.rng.below(3) as u16;
+
+        self.block(&scope, 0);
+        (0..stack_depth).for_each(|_| self.expression(&scope, 0));
+        self.emit(Command::Label(String::from("END")));
+        self.emit(Command::Goto(String::from("END")));
+
+        let mut sources = vec![(String::from("Main"), std::mem::take(&mut self.lines))];
+
+        for index in 0..function_count {
+            let class_name = self.functions[index].name.split('.').next().unwrap_or("Main").to_string();
+            let scope = Scope { function: Some(index), in_loop: false };
+
+            self.function(&scope, index);
+
+            let lines = std::mem::take(&mut self.lines);
+            match sources.iter_mut().find(|(name, _)| *name == class_name) {
+                Some((_, source)) => source.extend(lines),
+                None => sources.push((class_name.clone(), lines)),
+            };
+        }
+
+        Program {
+            files: sources.into_iter().map(|(name, lines)| SourceFile::new(&format!("{}.vm", name), &lines.join(""))).collect(),
+            stack_depth,
+        }
+    }
+
+    fn function(&mut self, scope: &Scope, index: usize) {
+        let function = &self.functions[index];
+        self.emit(Command::Function(function.name.clone(), function.local_count));
+        self.block(scope, 0);
+        self.expression(scope, 0);
+        self.emit(Command::Return);
+    }
+
+    // A few statements, which leave the stack as they found it
+    fn block(&mut self, scope: &Scope, depth: u32) {
+        for _ in 0..1 + self.rng.below(4) {
+            self.statement(scope, depth);
+        }
+    }
+
+    fn statement(&mut self, scope: &Scope, depth: u32) {
+        let nested = depth < MAX_BLOCK_DEPTH;
+
+        match self.rng.below(10) {
+            0 | 1 if nested => self.if_statement(scope, depth),
+            2 if nested => self.skip_statement(scope, depth),
+            3 if nested && scope.function.is_some() && !scope.in_loop => self.loop_statement(scope, depth),
+            4 if scope.function.is_some() => {
+                // Point `this` or `that` somewhere else within the watched range
+                let address = POINTER_BASE + self.rng.below(SEGMENT_SIZE as u64) as u16;
+                let pointer = self.rng.below(2) as u16;
+                self.emit(Command::Push(Segment::Constant, address));
+                self.emit(Command::Pop(Segment::Pointer, pointer));
+            }
+            _ => {
+                self.expression(scope, 0);
+                let (segment, index) = self.writable(scope);
+                self.emit(Command::Pop(segment, index));
+            }
+        };
+    }
+
+    fn if_statement(&mut self, scope: &Scope, depth: u32) {
+        let label = self.label("IF");
+
+        self.expression(scope, 0);
+        self.emit(Command::IfGoto(label.clone()));
+        self.block(scope, depth + 1);
+        self.emit(Command::Label(label));
+    }
+
+    fn skip_statement(&mut self, scope: &Scope, depth: u32) {
+        let label = self.label("SKIP");
+
+        self.emit(Command::Goto(label.clone()));
+        self.block(scope, depth + 1);
+        self.emit(Command::Label(label));
+    }
+
+    fn loop_statement(&mut self, scope: &Scope, depth: u32) {
+        let label = self.label("LOOP");
+        let body = Scope { in_loop: true, ..*scope };
+        let iterations = 1 + self.rng.below(3) as u16;
+
+        self.emit(Command::Push(Segment::Constant, iterations));
+        self.emit(Command::Pop(Segment::Local, 0));
+        self.emit(Command::Label(label.clone()));
+        self.block(&body, depth + 1);
+        self.emit(Command::Push(Segment::Local, 0));
+        self.emit(Command::Push(Segment::Constant, 1));
+        self.emit(Command::Arithmetic(ArithmeticCommand::Sub));
+        self.emit(Command::Pop(Segment::Local, 0));
+        self.emit(Command::Push(Segment::Local, 0));
+        self.emit(Command::IfGoto(label));
+    }
+
+    // Commands leaving exactly one more value on the stack
+    fn expression(&mut self, scope: &Scope, depth: u32) {
+        let leaf = depth >= MAX_EXPRESSION_DEPTH;
+        let callees = match scope.function {
+            Some(index) => index + 1..self.functions.len(),
+            None => 0..self.functions.len(),
+        };
+
+        match self.rng.below(10) {
+            0 | 1 if !leaf => {
+                self.expression(scope, depth + 1);
+                let operation = *self.rng.pick(&[ArithmeticCommand::Neg, ArithmeticCommand::Not]);
+                self.emit(Command::Arithmetic(operation));
+            }
+            2..=4 if !leaf => {
+                self.expression(scope, depth + 1);
+                self.expression(scope, depth + 1);
+                let operation = *self.rng.pick(&[
+                    ArithmeticCommand::Add,
+                    ArithmeticCommand::Sub,
+                    ArithmeticCommand::And,
+                    ArithmeticCommand::Or,
+                    ArithmeticCommand::Eq,
+                    ArithmeticCommand::Gt,
+                    ArithmeticCommand::Lt,
+                ]);
+                self.emit(Command::Arithmetic(operation));
+            }
+            5 if !leaf && !callees.is_empty() => {
+                let callee = callees.start + self.rng.below(callees.len() as u64) as usize;
+                let arg_count = self.functions[callee].arg_count;
+
+                (0..arg_count).for_each(|_| self.expression(scope, depth + 1));
+                self.emit(Command::Call(self.functions[callee].name.clone(), arg_count));
+            }
+            6 | 7 => {
+                let (segment, index) = self.readable(scope);
+                self.emit(Command::Push(segment, index));
+            }
+            _ => {
+                // Small constants make for more equal comparisons
+                let value = if self.rng.chance(50) { self.rng.below(4) } else { self.rng.below(32768) };
+                self.emit(Command::Push(Segment::Constant, value as u16));
+            }
+        };
+    }
+
+    fn readable(&mut self, scope: &Scope) -> (Segment, u16) {
+        match (self.rng.below(3), scope.function) {
+            (0, Some(index)) => {
+                let function = &self.functions[index];
+                match function.arg_count {
+                    0 => (Segment::Local, self.rng.below(function.local_count as u64) as u16),
+                    count => (Segment::Argument, self.rng.below(count as u64) as u16),
+                }
+            }
+            (1, Some(index)) => (Segment::Local, self.rng.below(self.functions[index].local_count as u64) as u16),
+            _ => self.global(),
+        }
+    }
+
+    fn writable(&mut self, scope: &Scope) -> (Segment, u16) {
+        match (self.rng.below(3), scope.function) {
+            (0, Some(index)) if self.functions[index].arg_count > 0 => {
+                (Segment::Argument, self.rng.below(self.functions[index].arg_count as u64) as u16)
+            }
+            // Local 0 is the loop counter
+            (1, Some(index)) if self.functions[index].local_count > 1 => {
+                (Segment::Local, 1 + self.rng.below(self.functions[index].local_count as u64 - 1) as u16)
+            }
+            _ => self.global(),
+        }
+    }
+
+    // Segments shared by all functions
+    fn global(&mut self) -> (Segment, u16) {
+        match self.rng.below(4) {
+            0 => (Segment::This, self.rng.below(SEGMENT_SIZE as u64) as u16),
+            1 => (Segment::That, self.rng.below(SEGMENT_SIZE as u64) as u16),
+            2 => (Segment::Temp, self.rng.below(8) as u16),
+            _ => (Segment::Static, self.rng.below(8) as u16),
+        }
+    }
+
+    fn label(&mut self, kind: &str) -> String {
+        self.label_count += 1;
+        format!("{}_{}", kind, self.label_count)
+    }
+
+    fn emit(&mut self, command: Command) {
+        self.lines.push(format!("{}\n", command));
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+
+use crate::command::{ArithmeticCommand, Command, Segment};
+use crate::emulator::{Stop, RAM_SIZE};
+use crate::parser::Instruction;
+use crate::{Diagnostics, Options, SourceFile};
+
+// Same memory layout as the translated code
+const SP: u16 = 0;
+const LCL: u16 = 1;
+const ARG: u16 = 2;
+const THIS: u16 = 3;
+const THAT: u16 = 4;
+const TEMP_BASE: u16 = 5;
+const STATIC_BASE: u16 = 16;
+
+const TRUE: u16 = 0xFFFF;
+const FALSE: u16 = 0;
+
+struct Step {
+    instruction: Instruction,
+    function_name: String,
+    // RAM address of a static variable, or index of the command to jump to
+    static_address: Option<u16>,
+    target: Option<usize>,
+}
+
+/// Executes VM commands directly, keeping the stack and segments in RAM exactly
+/// where the translated program would. Return addresses pushed by `call` are
+/// indices of VM commands rather than ROM addresses.
+pub struct Vm {
+    pub pc: usize,
+    pub steps: u64,
+    /// Local variables set to 0 by `function` commands so far: a single step here,
+    /// but a loop iteration each in the translated code.
+    pub locals: u64,
+    ram: Vec<u16>,
+    program: Vec<Step>,
+    functions: HashMap<String, usize>,
+    breakpoints: Vec<String>,
+    // Set when `run` stopped on a breakpoint, so the next `run` steps past it
+    on_breakpoint: bool,
+    static_count: usize,
+}
+
+impl Vm {
+    pub fn load(files: &[SourceFile], options: &Options) -> Result<Vm, Diagnostics> {
+        let (parsed_files, errors) = crate::parse_program(files, options);
+
+        if errors.iter().any(|error| error.is_error()) {
+            return Err(errors);
+        };
+
+        let bootstrap = crate::needs_bootstrap(&parsed_files, options);
+
+        // Statics get addresses in the order the assembler would meet them
+        let mut statics: HashMap<(String, u16), u16> = HashMap::new();
+        let mut functions: HashMap<String, usize> = HashMap::new();
+        let mut labels: HashMap<(String, String), usize> = HashMap::new();
+        let mut program = Vec::new();
+
+        for file in parsed_files {
+            let mut function_name = file.class_name.clone();
+
+            for instruction in file.instructions {
+                let mut static_address = None;
+
+                match &instruction.command {
+                    Command::Function(name, _) => {
+                        function_name = name.clone();
+                        functions.insert(name.clone(), program.len());
+                    }
+                    Command::Label(label) => {
+                        labels.insert((function_name.clone(), label.clone()), program.len());
+                    }
+                    Command::Push(Segment::Static, index) | Command::Pop(Segment::Static, index) => {
+                        let next_address = STATIC_BASE + statics.len() as u16;
+                        static_address = Some(*statics.entry((file.class_name.clone(), *index)).or_insert(next_address));
+                    }
+                    _ => (),
+                };
+
+                program.push(Step {
+                    instruction,
+                    function_name: function_name.clone(),
+                    static_address,
+                    target: None,
+                });
+            }
+        }
+
+        for step in program.iter_mut() {
+            step.target = match &step.instruction.command {
+                Command::Goto(label) | Command::IfGoto(label) => labels.get(&(step.function_name.clone(), label.clone())).copied(),
+                Command::Call(name, _) => functions.get(name).copied(),
+                _ => None,
+            };
+        }
+
+        let mut vm = Vm {
+            pc: 0,
+            steps: 0,
+            locals: 0,
+            ram: vec![0; RAM_SIZE],
+            program,
+            functions,
+            breakpoints: Vec::new(),
+            on_breakpoint: false,
+            static_count: statics.len(),
+        };
+
+        if bootstrap {
+            let registers = &options.registers;
+
+            vm.set_ram(SP, registers.sp);
+            vm.set_ram(LCL, registers.lcl);
+            vm.set_ram(ARG, registers.arg);
+            vm.set_ram(THIS, registers.this);
+            vm.set_ram(THAT, registers.that);
+
+            // Returning from the entry function ends the program
+            let end = vm.program.len() as u16;
+            vm.call(end, 0);
+            vm.pc = match vm.functions.get(&options.entry) {
+                Some(start) => *start,
+                None => vm.program.len(),
+            };
+        };
+
+        Ok(vm)
+    }
+
+    pub fn ram(&self, address: u16) -> u16 {
+        self.ram[address as usize % RAM_SIZE]
+    }
+
+    pub fn set_ram(&mut self, address: u16, value: u16) {
+        self.ram[address as usize % RAM_SIZE] = value;
+    }
+
+    /// How many static variables the program uses, stored from RAM[16] up.
+    pub fn static_count(&self) -> usize {
+        self.static_count
+    }
+
+    /// Whether a `function` command of the program defines `function_name`.
+    pub fn defines(&self, function_name: &str) -> bool {
+        self.functions.contains_key(function_name)
+    }
+
+    /// Stops `run` whenever `function_name` is entered.
+    pub fn add_breakpoint(&mut self, function_name: &str) {
+        self.breakpoints.push(String::from(function_name));
+    }
+
+    /// The command about to execute, if the program hasn't ended.
+    pub fn current(&self) -> Option<&Instruction> {
+        self.program.get(self.pc).map(|step| &step.instruction)
+    }
+
+    /// The function the command about to execute belongs to.
+    pub fn function_name(&self) -> Option<&str> {
+        self.program.get(self.pc).map(|step| step.function_name.as_str())
+    }
+
+    /// Runs until `max_steps` more commands have executed, a breakpoint is reached or the program halts.
+    /// After stopping on a breakpoint, the next `run` enters the function rather than stopping there again.
+    pub fn run(&mut self, max_steps: u64) -> Result<Stop, String> {
+        let limit = self.steps + max_steps;
+        let mut resuming = std::mem::take(&mut self.on_breakpoint);
+
+        loop {
+            if self.pc >= self.program.len() || self.is_halted() {
+                return Ok(Stop::Halted);
+            };
+
+            if !resuming && self.at_breakpoint() {
+                self.on_breakpoint = true;
+                return Ok(Stop::Breakpoint(self.pc as u16));
+            };
+
+            if self.steps >= limit {
+                return Ok(Stop::CycleLimit);
+            };
+
+            self.step()?;
+            resuming = false;
+        }
+    }
+
+
+    /// Executes the command at PC.
+    pub fn step(&mut self) -> Result<(), String> {
+        let step = match self.program.get(self.pc) {
+            Some(step) => step,
+            None => return Err(String::from("the program has ended")),
+        };
+        let command = step.instruction.command.clone();
+        let static_address = step.static_address;
+        let target = step.target;
+
+        self.steps += 1;
+        self.pc += 1;
+
+        match command {
+            Command::Push(segment, index) => {
+                let value = match segment {
+                    Segment::Constant => index,
+                    _ => {
+                        let address = self.address(segment, index, static_address);
+                        self.ram(address)
+                    }
+                };
+                self.push(value);
+            }
+            Command::Pop(segment, index) => {
+                let address = self.address(segment, index, static_address);
+                let value = self.pop();
+                self.set_ram(address, value);
+            }
+            Command::Arithmetic(command) => self.arithmetic(command),
+            Command::Label(_) | Command::Function(_, 0) => (),
+            Command::Function(_, local_count) => {
+                (0..local_count).for_each(|_| self.push(0));
+                self.locals += local_count as u64;
+            }
+            Command::Goto(label) => self.pc = self.jump_target(&label, target)?,
+            Command::IfGoto(label) => {
+                if self.pop() != 0 {
+                    self.pc = self.jump_target(&label, target)?;
+                };
+            }
+            Command::Call(name, arg_count) => {
+                let return_address = self.pc as u16;
+                self.call(return_address, arg_count);
+                self.pc = match target {
+                    Some(start) => start,
+                    None => return Err(format!("call to undefined function {}", name)),
+                };
+            }
+            Command::Return => {
+                let frame = self.ram(LCL);
+                let return_address = self.ram(frame.wrapping_sub(5));
+                let value = self.pop();
+                let arg = self.ram(ARG);
+
+                self.set_ram(arg, value);
+                self.set_ram(SP, arg.wrapping_add(1));
+                self.set_ram(THAT, self.ram(frame.wrapping_sub(1)));
+                self.set_ram(THIS, self.ram(frame.wrapping_sub(2)));
+                self.set_ram(ARG, self.ram(frame.wrapping_sub(3)));
+                self.set_ram(LCL, self.ram(frame.wrapping_sub(4)));
+
+                self.pc = return_address as usize;
+            }
+        };
+
+        Ok(())
+    }
+
+    fn push(&mut self, value: u16) {
+        let sp = self.ram(SP);
+        self.set_ram(sp, value);
+        self.set_ram(SP, sp.wrapping_add(1));
+    }
+
+    fn pop(&mut self) -> u16 {
+        let sp = self.ram(SP).wrapping_sub(1);
+        self.set_ram(SP, sp);
+        self.ram(sp)
+    }
+
+    fn call(&mut self, return_address: u16, arg_count: u16) {
+        self.push(return_address);
+        self.push(self.ram(LCL));
+        self.push(self.ram(ARG));
+        self.push(self.ram(THIS));
+        self.push(self.ram(THAT));
+
+        let sp = self.ram(SP);
+        self.set_ram(ARG, sp.wrapping_sub(5).wrapping_sub(arg_count));
+        self.set_ram(LCL, sp);
+    }
+
+    fn address(&self, segment: Segment, index: u16, static_address: Option<u16>) -> u16 {
+        match segment {
+            Segment::Local => self.ram(LCL).wrapping_add(index),
+            Segment::Argument => self.ram(ARG).wrapping_add(index),
+            Segment::This => self.ram(THIS).wrapping_add(index),
+            Segment::That => self.ram(THAT).wrapping_add(index),
+            Segment::Pointer => THIS + index,
+            Segment::Temp => TEMP_BASE + index,
+            Segment::Static => static_address.unwrap_or(STATIC_BASE),
+            Segment::Constant => unreachable!("constants have no address"),
+        }
+    }
+
+    fn arithmetic(&mut self, command: ArithmeticCommand) {
+        let unary = |vm: &mut Vm, operation: fn(u16) -> u16| {
+            let x = vm.pop();
+            vm.push(operation(x));
+        };
+        let binary = |vm: &mut Vm, operation: fn(u16, u16) -> u16| {
+            let y = vm.pop();
+            let x = vm.pop();
+            vm.push(operation(x, y));
+        };
+        let boolean = |condition: bool| if condition { TRUE } else { FALSE };
+
+        match command {
+            ArithmeticCommand::Add => binary(self, |x, y| x.wrapping_add(y)),
+            ArithmeticCommand::Sub => binary(self, |x, y| x.wrapping_sub(y)),
+            ArithmeticCommand::And => binary(self, |x, y| x & y),
+            ArithmeticCommand::Or => binary(self, |x, y| x | y),
+            ArithmeticCommand::Neg => unary(self, |x| x.wrapping_neg()),
+            ArithmeticCommand::Not => unary(self, |x| !x),
+            ArithmeticCommand::Eq => {
+                let y = self.pop();
+                let x = self.pop();
+                self.push(boolean(x == y));
+            }
+            ArithmeticCommand::Gt => {
+                let y = self.pop() as i16;
+                let x = self.pop() as i16;
+                self.push(boolean(x > y));
+            }
+            ArithmeticCommand::Lt => {
+                let y = self.pop() as i16;
+                let x = self.pop() as i16;
+                self.push(boolean(x < y));
+            }
+        };
+    }
+
+    fn jump_target(&self, label: &str, target: Option<usize>) -> Result<usize, String> {
+        match target {
+            Some(target) => Ok(target),
+            None => Err(format!("jump to undefined label {}", label)),
+        }
+    }
+
+    // `label END` directly followed by `goto END`
+    fn is_halted(&self) -> bool {
+        match &self.program[self.pc] {
+            Step { instruction: Instruction { command: Command::Goto(_), .. }, target: Some(target), .. } => {
+                *target + 1 == self.pc
+            }
+            _ => false,
+        }
+    }
+
+    // A `function` command of a function with a breakpoint
+    fn at_breakpoint(&self) -> bool {
+        match self.current().map(|instruction| &instruction.command) {
+            Some(Command::Function(name, _)) => self.breakpoints.contains(name),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,17 @@
+// Hands out assembly labels that cannot clash with each other, nor with the
+// labels of the VM program: VM identifiers never contain `$$`.
+pub(crate) struct LabelAllocator {
+    next: usize,
+}
+
+impl LabelAllocator {
+    pub(crate) fn new() -> LabelAllocator {
+        LabelAllocator { next: 0 }
+    }
+
+    pub(crate) fn unique(&mut self, prefix: &str, kind: &str) -> String {
+        let label = format!("{}$${}.{}", prefix, kind, self.next);
+        self.next += 1;
+        label
+    }
+}
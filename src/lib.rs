@@ -0,0 +1,230 @@
+#![allow(clippy::useless_format)]
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+pub mod args;
+pub mod assembler;
+pub mod command;
+pub mod diagnostic;
+// Test support, public only so that tests/ can drive it
+#[doc(hidden)]
+pub mod differential;
+pub mod emulator;
+pub mod error;
+pub mod files;
+#[doc(hidden)]
+pub mod generator;
+pub mod interpreter;
+pub mod manifest;
+pub mod optimizer;
+pub mod options;
+pub mod parser;
+pub mod test_script;
+mod check;
+mod codegen;
+mod labels;
+mod reachability;
+mod stack;
+
+pub use diagnostic::Diagnostic;
+pub use options::Options;
+
+use command::Command;
+use labels::LabelAllocator;
+use options::Bootstrap;
+use parser::Instruction;
+
+/// Hack assembly produced by the translator, one instruction or comment per line.
+pub struct Asm {
+    pub lines: Vec<String>,
+    /// Functions `Options::remove_dead_functions` left out, as `(function, file)` pairs in link order.
+    pub removed_functions: Vec<(String, String)>,
+}
+
+/// Errors and warnings collected while translating.
+pub type Diagnostics = Vec<Diagnostic>;
+
+/// A named `.vm` source, e.g. `Main.vm`, whose stem is used as the class name.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+}
+
+impl fmt::Display for Asm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.lines.iter().try_for_each(|line| f.write_str(line))
+    }
+}
+
+impl Asm {
+    /// Assembles the program into Hack machine words.
+    pub fn assemble(&self, file_name: &str) -> Result<Vec<u16>, Diagnostics> {
+        assembler::assemble(file_name, &self.to_string())
+    }
+
+    /// Where the assembler puts each static variable, e.g. `("Main.0", 16)`, in the order of their addresses.
+    pub fn static_addresses(&self, file_name: &str) -> Result<Vec<(String, u16)>, Diagnostics> {
+        assembler::variables(file_name, &self.to_string())
+    }
+
+    /// How many words the program takes in ROM: lines other than comments and labels.
+    pub fn instruction_count(&self) -> usize {
+        optimizer::instruction_count(&self.lines)
+    }
+}
+
+impl SourceFile {
+    pub fn new(name: &str, source: &str) -> SourceFile {
+        SourceFile {
+            name: String::from(name),
+            source: String::from(source),
+        }
+    }
+}
+
+/// Translates a single `.vm` file, without the bootstrap code.
+pub fn compile_source(name: &str, source: &str) -> Result<Asm, Diagnostics> {
+    let (file, errors) = parse_file(name, source);
+
+    if !errors.iter().any(|error| error.is_error()) {
+        let lines = translate_file(&mut LabelAllocator::new(), &file, &Options::default());
+        Ok(Asm { lines, removed_functions: Vec::new() })
+    } else {
+        Err(errors)
+    }
+}
+
+/// Translates a whole program: the bootstrap code followed by every file.
+pub fn compile_program(files: &[SourceFile], options: &Options) -> Result<Asm, Diagnostics> {
+    let (asm, errors) = translate_program(files, options);
+
+    if !errors.iter().any(|error| error.is_error()) {
+        Ok(asm)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Like `compile_program`, but also returns the output of the lines that did translate.
+pub fn translate_program(files: &[SourceFile], options: &Options) -> (Asm, Diagnostics) {
+    let (mut parsed_files, errors) = parse_program(files, options);
+    let bootstrap = needs_bootstrap(&parsed_files, options);
+
+    // Shared by every file so that generated labels stay unique in the merged output
+    let mut labels = LabelAllocator::new();
+
+    let names: Vec<&str> = files.iter().map(|file| file.name.as_str()).collect();
+    let mut lines = vec![format!("// Link order: {}\n", names.join(", "))];
+
+    let removed_functions = if options.remove_dead_functions {
+        reachability::unreachable_functions(&parsed_files, options, bootstrap)
+    } else {
+        Vec::new()
+    };
+
+    if !removed_functions.is_empty() {
+        let names: Vec<&str> = removed_functions.iter().map(|(name, _)| name.as_str()).collect();
+        lines.push(format!("// Removed unreachable functions: {}\n", names.join(", ")));
+
+        let removed: HashSet<&str> = names.into_iter().collect();
+        reachability::remove_functions(&mut parsed_files, &removed);
+    };
+
+    if bootstrap {
+        lines.append(&mut codegen::gen_init_code(&mut labels, options));
+    };
+
+    if options.shared_calls && (bootstrap || parsed_files.iter().any(|file| file.calls_or_returns())) {
+        lines.append(&mut codegen::gen_shared_routines(&mut labels));
+    };
+
+    for file in parsed_files.iter() {
+        lines.push(format!("// {}\n", file.name));
+        lines.append(&mut translate_file(&mut labels, file, options));
+    }
+
+    (Asm { lines, removed_functions }, errors)
+}
+
+pub(crate) fn parse_program(files: &[SourceFile], options: &Options) -> (Vec<ParsedFile>, Diagnostics) {
+    let mut parsed_files = Vec::new();
+    let mut errors = Vec::new();
+
+    for file in files {
+        let (parsed_file, mut file_errors) = parse_file(&file.name, &file.source);
+
+        parsed_files.push(parsed_file);
+        errors.append(&mut file_errors);
+    }
+
+    errors.append(&mut check::check_static_total(&parsed_files));
+
+    let entry = Some(options.entry.as_str()).filter(|_| needs_bootstrap(&parsed_files, options));
+    errors.append(&mut check::check_functions(&parsed_files, entry));
+    errors.append(&mut stack::check_stack_use(&parsed_files, entry, options.registers.sp));
+
+    (parsed_files, errors)
+}
+
+pub(crate) fn needs_bootstrap(files: &[ParsedFile], options: &Options) -> bool {
+    match options.bootstrap {
+        Bootstrap::Always => true,
+        Bootstrap::Never => false,
+        Bootstrap::Auto => files.iter().any(|file| file.defines_function(&options.entry)),
+    }
+}
+
+pub(crate) struct ParsedFile {
+    name: String,
+    class_name: String,
+    instructions: Vec<Instruction>,
+}
+
+impl ParsedFile {
+    fn defines_function(&self, name: &str) -> bool {
+        self.instructions.iter().any(|instruction| match &instruction.command {
+            Command::Function(function, _) => function == name,
+            _ => false,
+        })
+    }
+
+    fn calls_or_returns(&self) -> bool {
+        self.instructions.iter().any(|instruction| matches!(instruction.command, Command::Call(..) | Command::Return))
+    }
+}
+
+fn parse_file(name: &str, source: &str) -> (ParsedFile, Diagnostics) {
+    let class_name = match Path::new(name).file_stem() {
+        Some(stem) => stem.to_string_lossy().into_owned(),
+        None => String::from(name),
+    };
+
+    let (instructions, mut errors) = parser::parse_source(name, source);
+    let file = ParsedFile { name: String::from(name), class_name, instructions };
+
+    errors.append(&mut check::check_labels(&file));
+    errors.append(&mut check::check_statics(&file));
+    errors.append(&mut stack::check_stack(&file));
+
+    (file, errors)
+}
+
+fn translate_file(labels: &mut LabelAllocator, file: &ParsedFile, options: &Options) -> Vec<String> {
+    let mut output = Vec::new();
+    let mut function_name = file.class_name.clone();
+
+    for instruction in file.instructions.iter() {
+        if let Command::Function(name, _) = &instruction.command {
+            function_name = name.clone();
+        };
+
+        output.push(format!("// {}\n", instruction.command));
+        output.append(&mut codegen::compile_command(labels, &instruction.command, &file.class_name, &function_name, options));
+        output.push(format!("\n"));
+    };
+
+    output
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::Error;
+
+/// Name of the manifest looked up in the current directory when no input is given.
+pub const MANIFEST_NAME: &str = "vmcomp.toml";
+
+/// A `vmcomp.toml` project manifest, e.g.
+///
+/// ```toml
+/// sources = ["src"]
+/// libraries = ["../os"]
+/// entry = "Main.main"
+/// recursive = true
+///
+/// [output]
+/// path = "build/Pong.hack"
+/// emit = "hack"
+/// optimize = true
+/// remove_dead_functions = true
+/// ```
+///
+/// Paths are relative to the directory holding the manifest.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Files and directories to link, in order.
+    #[serde(default)]
+    pub sources: Vec<PathBuf>,
+    /// Directories linked after the sources, leaving out files a source replaces.
+    #[serde(default)]
+    pub libraries: Vec<PathBuf>,
+    /// The function the bootstrap code calls.
+    pub entry: Option<String>,
+    /// Whether directories are searched for `.vm` files recursively.
+    #[serde(default)]
+    pub recursive: bool,
+    /// Functions kept even when nothing reachable calls them.
+    #[serde(default)]
+    pub keep: Vec<String>,
+    #[serde(default)]
+    pub output: OutputSettings,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputSettings {
+    pub path: Option<PathBuf>,
+    /// `asm` or `hack`.
+    pub emit: Option<String>,
+    pub bootstrap: Option<bool>,
+    pub optimize: Option<bool>,
+    pub shared_calls: Option<bool>,
+    pub remove_dead_functions: Option<bool>,
+}
+
+impl Manifest {
+    pub fn read(path: &Path) -> Result<Manifest, Error> {
+        let text = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+        let mut manifest: Manifest = toml::from_str(&text)
+            .map_err(|err| Error::Usage(format!("{}: {}", path.display(), err)))?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        let resolve = |paths: &mut Vec<PathBuf>| paths.iter_mut().for_each(|path| *path = base.join(&path));
+
+        resolve(&mut manifest.sources);
+        resolve(&mut manifest.libraries);
+        manifest.output.path = manifest.output.path.map(|output| base.join(output));
+
+        Ok(manifest)
+    }
+}
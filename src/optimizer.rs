@@ -0,0 +1,201 @@
+use crate::Asm;
+
+// The tail of every push: RAM[SP] = D, SP = SP + 1
+const PUSH_D: [&str; 5] = ["@SP", "A=M", "M=D", "@SP", "M=M+1"];
+
+const NEG: [&str; 8] = ["@SP", "A=M-1", "D=M", "@0", "D=A-D", "@SP", "A=M-1", "M=D"];
+const BINARY_OPERATIONS: [&str; 4] = ["M=M+D", "M=M-D", "M=M&D", "M=M|D"];
+
+// What A is known to hold
+#[derive(Clone, PartialEq, Eq)]
+enum Address {
+    Symbol(String),
+    // RAM[SP] plus an offset, e.g. after `@SP` `A=M-1`
+    Stack(i8),
+}
+
+/// Rewrites the translated program into an equivalent one with fewer instructions.
+///
+/// Values pushed and immediately popped again stay in D, `@X` loads whose value is
+/// overwritten or already in A are dropped, and a few sequences get shorter forms.
+/// Only memory below SP is kept as it was: whatever the unoptimized code leaves
+/// above the top of the stack is not written anymore.
+pub fn optimize(asm: &Asm) -> Asm {
+    let mut lines = asm.lines.clone();
+
+    loop {
+        let count = instruction_count(&lines);
+
+        lines = rewrite_sequences(lines);
+        lines = remove_known_addresses(lines);
+
+        if instruction_count(&lines) == count {
+            return Asm { lines, removed_functions: asm.removed_functions.clone() };
+        };
+    }
+}
+
+pub(crate) fn instruction_count(lines: &[String]) -> usize {
+    lines.iter().filter(|line| is_code(line) && !line.trim().starts_with('(')).count()
+}
+
+// Instructions and labels, as the sequences being rewritten must not span a label
+fn is_code(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && !line.starts_with("//")
+}
+
+// Rewrites each matching sequence in place, keeping the comments around it
+fn rewrite_sequences(lines: Vec<String>) -> Vec<String> {
+    let positions: Vec<usize> = (0..lines.len()).filter(|index| is_code(&lines[*index])).collect();
+    let code: Vec<&str> = positions.iter().map(|index| lines[*index].trim()).collect();
+    let mut output: Vec<Option<String>> = lines.iter().cloned().map(Some).collect();
+    let mut next = 0;
+
+    while next < code.len() {
+        match rewrite(&code[next..]) {
+            Some((length, replacement)) => {
+                // The replacement takes the place of the last instructions, after the comment of the last command
+                let kept = length - replacement.len();
+
+                for (offset, position) in positions[next..next + length].iter().enumerate() {
+                    output[*position] = offset.checked_sub(kept).map(|index| format!("{}\n", replacement[index]));
+                }
+
+                next += length;
+            }
+            None => next += 1,
+        };
+    }
+
+    output.into_iter().flatten().collect()
+}
+
+// The length of the sequence starting `code` and what to replace it with
+fn rewrite(code: &[&str]) -> Option<(usize, Vec<String>)> {
+    let owned = |instructions: &[&str]| instructions.iter().map(|instruction| String::from(*instruction)).collect();
+
+    if code.starts_with(&NEG) {
+        return Some((NEG.len(), owned(&["@SP", "A=M-1", "M=-M"])));
+    };
+
+    // `@0` `D=A` is `D=0`, as long as A is set again before being used
+    if code.len() > 2 && (code[0] == "@0" || code[0] == "@1") && code[1] == "D=A" && code[2].starts_with('@') {
+        return Some((2, vec![format!("D={}", &code[0][1..])]));
+    };
+
+    // `@X` straight away followed by `@Y`
+    if code.len() > 1 && code[0].starts_with('@') && code[1].starts_with('@') {
+        return Some((1, Vec::new()));
+    };
+
+    if !code.starts_with(&PUSH_D) {
+        return None;
+    };
+
+    let rest = &code[PUSH_D.len()..];
+
+    // Push followed by a binary operation: y is already in D
+    if rest.len() >= 7 && rest[..4] == ["@SP", "A=M-1", "D=M", "A=A-1"]
+        && BINARY_OPERATIONS.contains(&rest[4]) && rest[5..7] == ["@SP", "M=M-1"] {
+        return Some((PUSH_D.len() + 7, owned(&["@SP", "A=M-1", rest[4]])));
+    };
+
+    // Push followed by a unary operation: compute it in D before pushing
+    if rest.len() >= 3 && rest[..2] == ["@SP", "A=M-1"] && (rest[2] == "M=!M" || rest[2] == "M=-M") {
+        let mut replacement = vec![rest[2].replace('M', "D")];
+        replacement.extend(owned(&PUSH_D));
+        return Some((PUSH_D.len() + 3, replacement));
+    };
+
+    // Push followed by if-goto: the condition is already in D
+    if rest.starts_with(&["@SP", "M=M-1", "A=M", "D=M"]) {
+        return Some((PUSH_D.len() + 4, Vec::new()));
+    };
+
+    // Push followed by pop: store D where the pop would
+    if rest.starts_with(&["@SP", "A=M-1", "D=M"]) {
+        let addressing = rest[3..].iter().take_while(|instruction| is_addressing(instruction)).count();
+        let end = 3 + addressing;
+
+        if addressing > 0 && rest[3].starts_with('@') && rest[end..].starts_with(&["M=D", "@SP", "M=M-1"]) {
+            let mut replacement: Vec<String> = owned(&rest[3..end]);
+            replacement.push(String::from("M=D"));
+            return Some((PUSH_D.len() + end + 3, replacement));
+        };
+    };
+
+    None
+}
+
+// Instructions pointing A somewhere, without touching D or the stack pointer
+fn is_addressing(instruction: &&str) -> bool {
+    match instruction.strip_prefix('@') {
+        Some(symbol) => !["SP", "R0", "0"].contains(&symbol),
+        None => *instruction == "A=M" || *instruction == "A=A+1",
+    }
+}
+
+// Drops `@X` (and `@SP` `A=M-1`) when A already holds that address
+fn remove_known_addresses(lines: Vec<String>) -> Vec<String> {
+    let mut output = Vec::new();
+    let mut known: Option<Address> = None;
+    let mut lines = lines.into_iter().peekable();
+
+    while let Some(line) = lines.next() {
+        let instruction = line.trim();
+
+        if !is_code(instruction) {
+            output.push(line);
+            continue;
+        };
+
+        if instruction.starts_with('(') {
+            // Jumps can land here with anything in A
+            known = None;
+        } else if let Some(symbol) = instruction.strip_prefix('@') {
+            if known == Some(Address::Symbol(String::from(symbol))) {
+                continue;
+            };
+
+            // `@SP` directly followed by `A=M-1` again
+            if symbol == "SP" {
+                let offset = lines.peek().and_then(|next| stack_offset(next.trim()));
+
+                if offset.is_some() && known == offset.map(Address::Stack) {
+                    lines.next();
+                    continue;
+                };
+            };
+
+            known = Some(Address::Symbol(String::from(symbol)));
+        } else {
+            let dest = match instruction.find('=') {
+                Some(equals) => &instruction[..equals],
+                None => "",
+            };
+
+            if dest.contains('A') {
+                known = match (&known, stack_offset(instruction), instruction) {
+                    (Some(Address::Symbol(symbol)), Some(offset), _) if symbol == "SP" => Some(Address::Stack(offset)),
+                    (Some(Address::Stack(offset)), _, "A=A-1") => offset.checked_sub(1).map(Address::Stack),
+                    (Some(Address::Stack(offset)), _, "A=A+1") => offset.checked_add(1).map(Address::Stack),
+                    _ => None,
+                };
+            };
+        };
+
+        output.push(line);
+    }
+
+    output
+}
+
+fn stack_offset(instruction: &str) -> Option<i8> {
+    match instruction {
+        "A=M-1" => Some(-1),
+        "A=M" => Some(0),
+        "A=M+1" => Some(1),
+        _ => None,
+    }
+}
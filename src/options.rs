@@ -0,0 +1,67 @@
+// Default initial values of the pointer registers
+pub const SP: u16 = 256;
+pub const LCL: u16 = 512;
+pub const ARG: u16 = 768;
+pub const THIS: u16 = 1024;
+pub const THAT: u16 = 1280;
+
+pub const ENTRY: &str = "Sys.init";
+
+/// Whether the program starts with the bootstrap code calling the entry function, `Sys.init` by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bootstrap {
+    /// Only when one of the files defines the entry function.
+    Auto,
+    Always,
+    Never,
+}
+
+/// Values the bootstrap code stores in SP, LCL, ARG, THIS and THAT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Registers {
+    pub sp: u16,
+    pub lcl: u16,
+    pub arg: u16,
+    pub this: u16,
+    pub that: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub bootstrap: Bootstrap,
+    pub registers: Registers,
+    /// The function the bootstrap code calls.
+    pub entry: String,
+    /// Whether call sites and returns jump to the shared `$$CALL` and `$$RETURN` routines
+    /// rather than saving and restoring the frame inline: smaller, but slower.
+    pub shared_calls: bool,
+    /// Whether to leave out the functions nothing reachable from the entry function calls.
+    pub remove_dead_functions: bool,
+    /// Functions kept even when unreachable, e.g. because they are only reached through a computed jump.
+    pub keep: Vec<String>,
+}
+
+impl Default for Registers {
+    fn default() -> Registers {
+        Registers {
+            sp: SP,
+            lcl: LCL,
+            arg: ARG,
+            this: THIS,
+            that: THAT,
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            bootstrap: Bootstrap::Auto,
+            registers: Registers::default(),
+            entry: String::from(ENTRY),
+            shared_calls: false,
+            remove_dead_functions: false,
+            keep: Vec::new(),
+        }
+    }
+}
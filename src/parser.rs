@@ -0,0 +1,310 @@
+use std::ops::Range;
+
+use crate::command::{ArithmeticCommand, Command, Segment};
+use crate::diagnostic::{Diagnostic, Location};
+
+// Largest value an A-instruction can load, which bounds constants, offsets and counts
+const MAX_VALUE: u16 = 32767;
+
+pub struct Instruction {
+    pub command: Command,
+    pub location: Location,
+}
+
+struct Token<'a> {
+    text: &'a str,
+    columns: Range<usize>,
+}
+
+enum Number {
+    Valid(u16),
+    OutOfRange,
+    NotAnInteger,
+}
+
+struct ParseError {
+    code: &'static str,
+    message: String,
+    columns: Range<usize>,
+    help: Option<String>,
+}
+
+impl ParseError {
+    fn new(code: &'static str, message: String, columns: Range<usize>) -> ParseError {
+        ParseError { code, message, columns, help: None }
+    }
+
+    fn with_help(mut self, help: &str) -> ParseError {
+        self.help = Some(String::from(help));
+        self
+    }
+}
+
+pub fn parse_source(file_name: &str, source: &str) -> (Vec<Instruction>, Vec<Diagnostic>) {
+    let mut instructions = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let tokens = tokenize(line);
+
+        if tokens.is_empty() {
+            continue;
+        };
+
+        let columns = tokens[0].columns.start..tokens[tokens.len() - 1].columns.end;
+
+        match parse_tokens(&tokens) {
+            Ok(command) => instructions.push(Instruction {
+                command,
+                location: Location::new(file_name, index + 1, columns, line.trim_end()),
+            }),
+            Err(err) => {
+                let location = Location::new(file_name, index + 1, err.columns, line.trim_end());
+                let diagnostic = Diagnostic::error(err.code, err.message, Some(location));
+
+                errors.push(match err.help {
+                    Some(help) => diagnostic.with_help(help),
+                    None => diagnostic,
+                });
+            }
+        };
+    }
+
+    (instructions, errors)
+}
+
+pub fn parse_line(line: &str) -> Result<Command, String> {
+    let tokens = tokenize(line);
+
+    if tokens.is_empty() {
+        return Err(String::from("Syntax error: empty line"));
+    };
+
+    parse_tokens(&tokens).map_err(|err| err.message)
+}
+
+// Splits a line on whitespace, dropping everything after `//`
+fn tokenize(line: &str) -> Vec<Token<'_>> {
+    let code = match line.find("//") {
+        Some(comment) => &line[..comment],
+        None => line,
+    };
+
+    let mut tokens = Vec::new();
+    let mut start = None;
+
+    for (index, c) in code.char_indices().chain(Some((code.len(), ' '))) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(index),
+            (Some(from), true) => {
+                tokens.push(Token { text: &code[from..index], columns: from..index });
+                start = None;
+            }
+            _ => (),
+        }
+    }
+
+    tokens
+}
+
+fn parse_tokens(tokens: &[Token]) -> Result<Command, ParseError> {
+    let operation = &tokens[0];
+    let args = &tokens[1..];
+
+    match operation.text {
+        "push" => parse_push(operation, args),
+        "pop" => parse_pop(operation, args),
+        "label" => parse_label(operation, args).map(Command::Label),
+        "goto" => parse_label(operation, args).map(Command::Goto),
+        "if-goto" => parse_label(operation, args).map(Command::IfGoto),
+        "function" => parse_function(operation, args),
+        "call" => parse_call(operation, args),
+        "return" => expect_args(operation, args, 0).map(|_| Command::Return),
+        otherwise => match ArithmeticCommand::from_name(otherwise) {
+            Some(command) => expect_args(operation, args, 0).map(|_| Command::Arithmetic(command)),
+            None => Err(ParseError::new(
+                "E0001",
+                format!("Unsupported operation: {}", otherwise),
+                operation.columns.clone(),
+            ).with_help("expected one of push, pop, add, sub, neg, eq, gt, lt, and, or, not, label, goto, if-goto, function, call, return")),
+        },
+    }
+}
+
+fn expect_args(operation: &Token, args: &[Token], count: usize) -> Result<(), ParseError> {
+    if args.len() == count {
+        return Ok(());
+    };
+
+    let plural = match count {
+        0 => "no argument",
+        1 => "one argument",
+        _ => "two arguments",
+    };
+    let received: Vec<&str> = args.iter().map(|arg| arg.text).collect();
+
+    // Point at the superfluous arguments, or at the operation when some are missing
+    let columns = if args.len() > count {
+        args[count].columns.start..args[args.len() - 1].columns.end
+    } else {
+        operation.columns.clone()
+    };
+
+    Err(ParseError::new(
+        "E0002",
+        format!("{} takes {}, received {:?}", operation.text, plural, received),
+        columns,
+    ))
+}
+
+fn parse_push(operation: &Token, args: &[Token]) -> Result<Command, ParseError> {
+    expect_args(operation, args, 2)?;
+
+    let segment = match Segment::from_name(args[0].text) {
+        Some(segment) => segment,
+        None => return Err(ParseError::new(
+            "E0003",
+            format!("push first argument must be a segment, received {}", args[0].text),
+            args[0].columns.clone(),
+        ).with_help("expected one of local, argument, this, that, temp, static, pointer, constant")),
+    };
+
+    Ok(Command::Push(segment, parse_index(operation, segment, &args[1])?))
+}
+
+fn parse_pop(operation: &Token, args: &[Token]) -> Result<Command, ParseError> {
+    expect_args(operation, args, 2)?;
+
+    let segment = match Segment::from_name(args[0].text) {
+        Some(Segment::Constant) => return Err(ParseError::new(
+            "E0003",
+            String::from("pop cannot write to the constant segment"),
+            args[0].columns.clone(),
+        ).with_help("to discard the top of the stack, use `pop temp 0`")),
+        Some(segment) => segment,
+        None => return Err(ParseError::new(
+            "E0003",
+            format!("pop first argument must be a segment, received {}", args[0].text),
+            args[0].columns.clone(),
+        ).with_help("expected one of local, argument, this, that, temp, static, pointer")),
+    };
+
+    Ok(Command::Pop(segment, parse_index(operation, segment, &args[1])?))
+}
+
+fn parse_index(operation: &Token, segment: Segment, arg: &Token) -> Result<u16, ParseError> {
+    let max = match segment {
+        Segment::Temp => 7,
+        Segment::Pointer => 1,
+        _ => MAX_VALUE,
+    };
+
+    match parse_number(arg.text, max) {
+        Number::Valid(val) => Ok(val),
+        Number::OutOfRange => {
+            let bounds = match segment {
+                Segment::Pointer => String::from("0 or 1"),
+                _ => format!("between 0 and {}", max),
+            };
+            let error = ParseError::new(
+                "E0005",
+                format!("{} {} argument must be {}, received {}", operation.text, segment.name(), bounds, arg.text),
+                arg.columns.clone(),
+            );
+
+            Err(match segment {
+                Segment::Pointer => error.with_help("pointer 0 is THIS and pointer 1 is THAT"),
+                Segment::Constant if arg.text.starts_with('-') => error.with_help("push the opposite value, then use `neg`"),
+                Segment::Constant => error.with_help("an A-instruction only holds 15 bits"),
+                _ => error,
+            })
+        }
+        Number::NotAnInteger => Err(ParseError::new(
+            "E0004",
+            format!("{} {} argument must be an integer, received {}", operation.text, segment.name(), arg.text),
+            arg.columns.clone(),
+        )),
+    }
+}
+
+// Tells integers outside of `0..=max`, negative or too large for any type, from non-integers
+fn parse_number(text: &str, max: u16) -> Number {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Number::NotAnInteger;
+    };
+
+    match text.parse::<i64>() {
+        Ok(val) if (0..=i64::from(max)).contains(&val) => Number::Valid(val as u16),
+        _ => Number::OutOfRange,
+    }
+}
+
+// Letters, digits, `_`, `.` and `:`, not starting with a digit
+fn is_identifier(text: &str) -> bool {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == ':';
+
+    match text.chars().next() {
+        Some(first) => !first.is_ascii_digit() && text.chars().all(valid_char),
+        None => false,
+    }
+}
+
+fn parse_identifier(operation: &Token, arg: &Token) -> Result<String, ParseError> {
+    if !is_identifier(arg.text) {
+        return Err(ParseError::new(
+            "E0007",
+            format!("{} argument must be an identifier, received {}", operation.text, arg.text),
+            arg.columns.clone(),
+        ).with_help("identifiers are made of letters, digits, `_`, `.` and `:`, and don't start with a digit"));
+    };
+
+    Ok(String::from(arg.text))
+}
+
+fn parse_label(operation: &Token, args: &[Token]) -> Result<String, ParseError> {
+    expect_args(operation, args, 1)?;
+
+    parse_identifier(operation, &args[0])
+}
+
+fn parse_count(operation: &Token, arg: &Token) -> Result<u16, ParseError> {
+    match parse_number(arg.text, MAX_VALUE) {
+        Number::Valid(val) => Ok(val),
+        Number::OutOfRange => Err(ParseError::new(
+            "E0005",
+            format!("{} second argument must be between 0 and {}, received {}", operation.text, MAX_VALUE, arg.text),
+            arg.columns.clone(),
+        )),
+        Number::NotAnInteger => Err(ParseError::new(
+            "E0004",
+            format!("{} second argument must be an integer, received {}", operation.text, arg.text),
+            arg.columns.clone(),
+        )),
+    }
+}
+
+fn parse_function(operation: &Token, args: &[Token]) -> Result<Command, ParseError> {
+    expect_args(operation, args, 2)?;
+
+    let name = parse_identifier(operation, &args[0])?;
+
+    if !name.contains('.') {
+        return Err(ParseError::new(
+            "E0006",
+            format!("function first argument must be class.name, received {}", args[0].text),
+            args[0].columns.clone(),
+        ).with_help("functions are named after the class declaring them, e.g. `Main.main`"));
+    }
+
+    Ok(Command::Function(name, parse_count(operation, &args[1])?))
+}
+
+fn parse_call(operation: &Token, args: &[Token]) -> Result<Command, ParseError> {
+    expect_args(operation, args, 2)?;
+
+    let name = parse_identifier(operation, &args[0])?;
+
+    Ok(Command::Call(name, parse_count(operation, &args[1])?))
+}
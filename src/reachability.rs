@@ -0,0 +1,71 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::check::function_bodies;
+use crate::command::Command;
+use crate::options::Options;
+use crate::ParsedFile;
+
+/// Functions nothing reachable calls, as `(function, file)` pairs in link order. The walk starts at
+/// the entry function when `bootstrap` is set, else at the first command of the program, and also
+/// at the code outside functions and at the functions of `options.keep`.
+pub(crate) fn unreachable_functions(files: &[ParsedFile], options: &Options, bootstrap: bool) -> Vec<(String, String)> {
+    let mut callees: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut functions = Vec::new();
+    let mut pending: Vec<&str> = options.keep.iter().map(String::as_str).collect();
+
+    for file in files {
+        for (index, (name, body)) in function_bodies(file).into_iter().enumerate() {
+            let calls = body.iter().filter_map(|instruction| match &instruction.command {
+                Command::Call(callee, _) => Some(callee.as_str()),
+                _ => None,
+            });
+
+            // The first body holds the code before the first `function` of the file, which runs
+            // if it is reached
+            if index == 0 {
+                pending.extend(calls);
+                continue;
+            };
+
+            callees.entry(name).or_default().extend(calls);
+            functions.push((name, file.name.as_str()));
+        }
+    }
+
+    if bootstrap {
+        pending.push(&options.entry);
+    } else if let Some(first) = files.iter().flat_map(|file| file.instructions.first()).next() {
+        if let Command::Function(name, _) = &first.command {
+            pending.push(name);
+        };
+    };
+
+    let mut reached = HashSet::new();
+
+    while let Some(name) = pending.pop() {
+        if reached.insert(name) {
+            pending.extend(callees.get(name).into_iter().flatten());
+        };
+    }
+
+    functions
+        .into_iter()
+        .filter(|(name, _)| !reached.contains(name))
+        .map(|(name, file)| (String::from(name), String::from(file)))
+        .collect()
+}
+
+/// Drops the body of every function in `removed`, from its `function` command to the next one.
+pub(crate) fn remove_functions(files: &mut [ParsedFile], removed: &HashSet<&str>) {
+    for file in files.iter_mut() {
+        let mut removing = false;
+
+        file.instructions.retain(|instruction| {
+            if let Command::Function(name, _) = &instruction.command {
+                removing = removed.contains(name.as_str());
+            };
+
+            !removing
+        });
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::check::function_bodies;
+use crate::command::{ArithmeticCommand, Command};
+use crate::diagnostic::Diagnostic;
+use crate::parser::Instruction;
+use crate::{Diagnostics, ParsedFile};
+
+// The bootstrap code starts the stack at SP, and the heap starts at RAM[2048]
+const STACK_END: usize = 2048;
+
+// A call saves the return address, LCL, ARG, THIS and THAT above the arguments
+const FRAME_SIZE: usize = 5;
+
+/// How much stack a function takes, leaving out its callees.
+struct FunctionStack<'a> {
+    name: &'a str,
+    /// Local variables plus the most values the function has on the stack at once.
+    max_depth: usize,
+    /// Each call, with the stack depth before it, arguments included.
+    calls: Vec<(usize, &'a str)>,
+}
+
+// Stack underflow, mismatched depths where paths join, and returns without a value
+pub(crate) fn check_stack(file: &ParsedFile) -> Diagnostics {
+    analyze(file).1
+}
+
+// Follows the deepest chain of calls from the entry function to see whether the
+// stack can run past RAM[2047]. Recursive calls are left out, as their depth is unknown.
+pub(crate) fn check_stack_use(files: &[ParsedFile], entry: Option<&str>, sp: u16) -> Diagnostics {
+    let entry = match entry {
+        Some(entry) => entry,
+        None => return Vec::new(),
+    };
+
+    let mut functions = HashMap::new();
+
+    for file in files {
+        for function in analyze(file).0 {
+            functions.entry(function.name).or_insert(function);
+        }
+    }
+
+    let mut search = Search { functions: &functions, deepest: HashMap::new(), visiting: HashSet::new(), recursive: false };
+    let (depth, chain) = search.deepest(entry);
+    let last = sp as usize + FRAME_SIZE + depth - 1;
+
+    if last < STACK_END {
+        return Vec::new();
+    };
+
+    let mut help = format!("deepest calls: {}", chain.join(" > "));
+
+    if search.recursive {
+        help.push_str(", not counting recursive calls");
+    };
+
+    vec![Diagnostic::warning(
+        "W0006",
+        format!("the stack can reach RAM[{}], past its end at RAM[{}]", last, STACK_END - 1),
+        None,
+    ).with_help(help)]
+}
+
+struct Search<'a, 'b> {
+    functions: &'b HashMap<&'a str, FunctionStack<'a>>,
+    deepest: HashMap<&'a str, (usize, Vec<&'a str>)>,
+    visiting: HashSet<&'a str>,
+    recursive: bool,
+}
+
+impl<'a, 'b> Search<'a, 'b> {
+    // The most stack `name` and its callees take, and the chain of calls taking it
+    fn deepest(&mut self, name: &'a str) -> (usize, Vec<&'a str>) {
+        if let Some(deepest) = self.deepest.get(name) {
+            return deepest.clone();
+        };
+
+        let function = match self.functions.get(name) {
+            Some(function) => function,
+            None => return (0, vec![name]),
+        };
+
+        self.visiting.insert(name);
+
+        let mut deepest = (function.max_depth, vec![name]);
+
+        for (depth, callee) in function.calls.iter() {
+            if self.visiting.contains(callee) {
+                self.recursive = true;
+                continue;
+            };
+
+            let (callee_depth, callee_chain) = self.deepest(callee);
+
+            if depth + FRAME_SIZE + callee_depth > deepest.0 {
+                deepest = (depth + FRAME_SIZE + callee_depth, [name].iter().copied().chain(callee_chain).collect());
+            };
+        }
+
+        self.visiting.remove(name);
+        self.deepest.insert(name, deepest.clone());
+
+        deepest
+    }
+}
+
+fn analyze(file: &ParsedFile) -> (Vec<FunctionStack<'_>>, Diagnostics) {
+    let mut diagnostics = Vec::new();
+    let functions = function_bodies(file)
+        .into_iter()
+        .filter(|(_, body)| !body.is_empty())
+        .map(|(name, body)| analyze_body(name, body, &mut diagnostics))
+        .collect();
+
+    (functions, diagnostics)
+}
+
+// Walks every path through the body, recording the stack depth each instruction is
+// first reached with, relative to the depth at the start of the body
+fn analyze_body<'a>(name: &'a str, body: &'a [Instruction], diagnostics: &mut Diagnostics) -> FunctionStack<'a> {
+    let labels: HashMap<&str, usize> = body.iter().enumerate().filter_map(|(index, instruction)| match &instruction.command {
+        Command::Label(label) => Some((label.as_str(), index)),
+        _ => None,
+    }).collect();
+
+    let locals = match body[0].command {
+        Command::Function(_, local_count) => local_count as usize,
+        _ => 0,
+    };
+
+    let mut function = FunctionStack { name, max_depth: locals, calls: Vec::new() };
+    let mut depths: Vec<Option<usize>> = vec![None; body.len()];
+    let mut pending = vec![(0, 0)];
+
+    while let Some((index, depth)) = pending.pop() {
+        let instruction = match body.get(index) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+
+        match depths[index] {
+            Some(previous) if previous != depth => {
+                diagnostics.push(Diagnostic::warning(
+                    "W0004",
+                    format!("`{}` is reached with {} and with {} value(s) on the stack", instruction.command, previous, depth),
+                    Some(instruction.location.clone()),
+                ).with_help(format!("every path to a label of {} must leave the same number of values", name)));
+                continue;
+            }
+            Some(_) => continue,
+            None => depths[index] = Some(depth),
+        };
+
+        let (popped, pushed) = stack_effect(&instruction.command);
+
+        if depth < popped {
+            diagnostics.push(match instruction.command {
+                Command::Return => Diagnostic::warning(
+                    "W0005",
+                    format!("{} returns without a value on the stack", name),
+                    Some(instruction.location.clone()),
+                ).with_help(String::from("a function returning nothing pushes constant 0 before `return`")),
+                _ => Diagnostic::warning(
+                    "W0003",
+                    format!("`{}` pops {} value(s), but the stack of {} holds {} here", instruction.command, popped, name, depth),
+                    Some(instruction.location.clone()),
+                ).with_help(String::from("values below the start of a function's stack belong to its caller")),
+            });
+        };
+
+        let next_depth = depth.saturating_sub(popped) + pushed;
+        function.max_depth = function.max_depth.max(locals + next_depth);
+
+        match &instruction.command {
+            Command::Call(callee, _) => {
+                function.calls.push((locals + depth, callee));
+                pending.push((index + 1, next_depth));
+            }
+            Command::Goto(label) => pending.extend(labels.get(label.as_str()).map(|target| (*target, next_depth))),
+            Command::IfGoto(label) => {
+                pending.extend(labels.get(label.as_str()).map(|target| (*target, next_depth)));
+                pending.push((index + 1, next_depth));
+            }
+            Command::Return => (),
+            _ => pending.push((index + 1, next_depth)),
+        };
+    }
+
+    function
+}
+
+// How many values a command pops, then pushes
+fn stack_effect(command: &Command) -> (usize, usize) {
+    match command {
+        Command::Push(..) => (0, 1),
+        Command::Pop(..) | Command::IfGoto(_) => (1, 0),
+        Command::Arithmetic(ArithmeticCommand::Neg) | Command::Arithmetic(ArithmeticCommand::Not) => (1, 1),
+        Command::Arithmetic(_) => (2, 1),
+        Command::Call(_, arg_count) => (*arg_count as usize, 1),
+        Command::Return => (1, 0),
+        Command::Label(_) | Command::Goto(_) | Command::Function(..) => (0, 0),
+    }
+}
@@ -0,0 +1,434 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::emulator::Cpu;
+use crate::files;
+
+/// A command of a Nand2Tetris CPU emulator test script (`.tst`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptCommand {
+    Load(String),
+    OutputFile(String),
+    CompareTo(String),
+    OutputList(Vec<OutputColumn>),
+    Set(Register, i32),
+    Repeat(u64, Vec<ScriptCommand>),
+    Tick,
+    Tock,
+    TickTock,
+    Output,
+    Echo(String),
+    ClearEcho,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    Ram(u16),
+    A,
+    D,
+    Pc,
+    Time,
+}
+
+/// One column of `output-list`, e.g. `RAM[256]%D1.6.1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputColumn {
+    pub name: String,
+    pub register: Register,
+    pub format: char,
+    pub left: usize,
+    pub width: usize,
+    pub right: usize,
+}
+
+/// Where the output of a script first differs from its `.cmp` file, on a 1-based line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub line: usize,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// State of a running script: the loaded program and what was output so far.
+pub struct ScriptRun {
+    pub cpu: Option<Cpu>,
+    pub output: Vec<String>,
+    pub output_file: Option<String>,
+    pub compare_to: Option<String>,
+    pub echo: Vec<String>,
+    columns: Vec<OutputColumn>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Separator,
+    Open,
+    Close,
+}
+
+pub fn parse_script(source: &str) -> Result<Vec<ScriptCommand>, String> {
+    let tokens = tokenize(&strip_comments(source));
+    let mut position = 0;
+
+    let commands = parse_block(&tokens, &mut position)?;
+
+    match tokens.get(position) {
+        Some(_) => Err(String::from("unexpected }")),
+        None => Ok(commands),
+    }
+}
+
+/// Runs `commands`, calling `load` to get the CPU for each `load` command.
+pub fn run_script<F>(commands: &[ScriptCommand], load: &mut F) -> Result<ScriptRun, String>
+where
+    F: FnMut(&str) -> Result<Cpu, String>,
+{
+    let mut run = ScriptRun {
+        cpu: None,
+        output: Vec::new(),
+        output_file: None,
+        compare_to: None,
+        echo: Vec::new(),
+        columns: Vec::new(),
+    };
+
+    execute(commands, &mut run, load)?;
+
+    Ok(run)
+}
+
+/// Loads the program a script in `dir` names, e.g. `BasicTest.asm`: the translation of
+/// BasicTest.vm if there is one, else of every `.vm` file of `dir`, else the file itself.
+/// The VM code comes first because an .asm file next to it may be stale.
+pub fn load(dir: &Path, name: &str) -> Result<Cpu, String> {
+    let path = dir.join(name);
+    let vm_file = path.with_extension("vm");
+
+    let sources = if vm_file.is_file() {
+        vec![files::read_file(&vm_file).map_err(|err| err.to_string())?]
+    } else {
+        files::read_dir(dir).map_err(|err| err.to_string())?
+    };
+
+    let source = if !sources.is_empty() {
+        match crate::compile_program(&sources, &crate::Options::default()) {
+            Ok(asm) => asm.to_string(),
+            Err(diagnostics) => return Err(diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect()),
+        }
+    } else {
+        fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?
+    };
+
+    let loaded = if name.ends_with(".hack") && sources.is_empty() {
+        Cpu::from_hack(name, &source)
+    } else {
+        Cpu::from_asm(name, &source)
+    };
+
+    loaded.map_err(|diagnostics| diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect())
+}
+
+/// Compares output lines with the contents of a `.cmp` file, ignoring trailing whitespace.
+pub fn compare(output: &[String], expected: &str) -> Result<(), Mismatch> {
+    let expected: Vec<&str> = expected.lines().map(|line| line.trim_end()).filter(|line| !line.is_empty()).collect();
+
+    for line in 0..expected.len().max(output.len()) {
+        let expected_line = expected.get(line).copied().unwrap_or("");
+        let actual_line = output.get(line).map(|line| line.trim_end()).unwrap_or("");
+
+        if expected_line != actual_line {
+            return Err(Mismatch {
+                line: line + 1,
+                expected: String::from(expected_line),
+                actual: String::from(actual_line),
+            });
+        };
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "comparison failure at line {}\n  expected: {}\n  actual:   {}", self.line, self.expected, self.actual)
+    }
+}
+
+fn execute<F>(commands: &[ScriptCommand], run: &mut ScriptRun, load: &mut F) -> Result<(), String>
+where
+    F: FnMut(&str) -> Result<Cpu, String>,
+{
+    for command in commands {
+        match command {
+            ScriptCommand::Load(file) => run.cpu = Some(load(file)?),
+            ScriptCommand::OutputFile(file) => run.output_file = Some(file.clone()),
+            ScriptCommand::CompareTo(file) => run.compare_to = Some(file.clone()),
+            ScriptCommand::OutputList(columns) => {
+                run.columns = columns.clone();
+                let header: Vec<String> = columns.iter().map(format_header).collect();
+                run.output.push(format!("|{}|", header.join("|")));
+            }
+            ScriptCommand::Set(register, value) => {
+                let cpu = loaded(&mut run.cpu)?;
+                let value = *value as u16;
+
+                match register {
+                    Register::Ram(address) => cpu.set_ram(*address, value),
+                    Register::A => cpu.a = value,
+                    Register::D => cpu.d = value,
+                    Register::Pc => cpu.pc = value,
+                    Register::Time => return Err(String::from("time cannot be set")),
+                };
+            }
+            ScriptCommand::Repeat(count, body) => {
+                for _ in 0..*count {
+                    execute(body, run, load)?;
+                }
+            }
+            // A whole instruction runs on each tock
+            ScriptCommand::Tick => (),
+            ScriptCommand::Tock | ScriptCommand::TickTock => loaded(&mut run.cpu)?.step(),
+            ScriptCommand::Output => {
+                let cpu = loaded(&mut run.cpu)?;
+                let values: Vec<String> = run.columns.iter().map(|column| format_value(column, cpu)).collect();
+                run.output.push(format!("|{}|", values.join("|")));
+            }
+            ScriptCommand::Echo(text) => run.echo.push(text.clone()),
+            ScriptCommand::ClearEcho => run.echo.clear(),
+        };
+    }
+
+    Ok(())
+}
+
+fn loaded(cpu: &mut Option<Cpu>) -> Result<&mut Cpu, String> {
+    match cpu {
+        Some(cpu) => Ok(cpu),
+        None => Err(String::from("no program loaded")),
+    }
+}
+
+fn format_header(column: &OutputColumn) -> String {
+    let total = column.left + column.width + column.right;
+    let name: String = column.name.chars().take(total).collect();
+    let left = (total - name.chars().count()) / 2;
+
+    format!("{}{:<width$}", " ".repeat(left), name, width = total - left)
+}
+
+fn format_value(column: &OutputColumn, cpu: &Cpu) -> String {
+    let value = match column.register {
+        Register::Ram(address) => cpu.ram(address),
+        Register::A => cpu.a,
+        Register::D => cpu.d,
+        Register::Pc => cpu.pc,
+        Register::Time => cpu.cycles as u16,
+    };
+
+    let text = match column.format {
+        'X' => format!("{:04X}", value),
+        'B' => format!("{:016b}", value),
+        _ => (value as i16).to_string(),
+    };
+
+    format!(
+        "{}{:>width$}{}",
+        " ".repeat(column.left),
+        text,
+        " ".repeat(column.right),
+        width = column.width
+    )
+}
+
+fn strip_comments(source: &str) -> String {
+    let mut result = String::new();
+    let mut rest = source;
+
+    while !rest.is_empty() {
+        if let Some(line_comment) = rest.strip_prefix("//") {
+            rest = match line_comment.find('\n') {
+                Some(end) => &line_comment[end..],
+                None => "",
+            };
+        } else if let Some(block_comment) = rest.strip_prefix("/*") {
+            rest = match block_comment.find("*/") {
+                Some(end) => &block_comment[end + 2..],
+                None => "",
+            };
+            result.push(' ');
+        } else {
+            let c = rest.chars().next().unwrap();
+            result.push(c);
+            rest = &rest[c.len_utf8()..];
+        };
+    }
+
+    result
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut chars = source.chars();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            ',' | ';' | '!' => Some(Token::Separator),
+            '{' => Some(Token::Open),
+            '}' => Some(Token::Close),
+            // Quoted text, as in `echo "Make sure ..."`
+            '"' => {
+                word.extend(chars.by_ref().take_while(|c| *c != '"'));
+                None
+            }
+            c if c.is_whitespace() => None,
+            c => {
+                word.push(c);
+                continue;
+            }
+        };
+
+        if !word.is_empty() {
+            tokens.push(Token::Word(word.clone()));
+            word.clear();
+        };
+
+        if let Some(token) = token {
+            tokens.push(token);
+        };
+    }
+
+    if !word.is_empty() {
+        tokens.push(Token::Word(word));
+    };
+
+    tokens
+}
+
+fn parse_block(tokens: &[Token], position: &mut usize) -> Result<Vec<ScriptCommand>, String> {
+    let mut commands = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+
+    while let Some(token) = tokens.get(*position) {
+        *position += 1;
+
+        match token {
+            Token::Word(word) => words.push(word.clone()),
+            Token::Separator => {
+                if !words.is_empty() {
+                    commands.push(parse_command(&words)?);
+                    words.clear();
+                };
+            }
+            Token::Open => {
+                let count = match words.as_slice() {
+                    [repeat] if repeat == "repeat" => return Err(String::from("repeat requires a count")),
+                    [repeat, count] if repeat == "repeat" => match count.parse::<u64>() {
+                        Ok(count) => count,
+                        Err(_) => return Err(format!("repeat count must be an integer, received {}", count)),
+                    },
+                    _ => return Err(format!("unexpected {{ after {}", words.join(" "))),
+                };
+
+                let body = parse_block(tokens, position)?;
+
+                match tokens.get(*position) {
+                    Some(Token::Close) => *position += 1,
+                    _ => return Err(String::from("missing } to close repeat")),
+                };
+
+                commands.push(ScriptCommand::Repeat(count, body));
+                words.clear();
+            }
+            Token::Close => {
+                *position -= 1;
+                break;
+            }
+        };
+    }
+
+    if !words.is_empty() {
+        commands.push(parse_command(&words)?);
+    };
+
+    Ok(commands)
+}
+
+fn parse_command(words: &[String]) -> Result<ScriptCommand, String> {
+    let args = &words[1..];
+    let single_arg = || match args {
+        [arg] => Ok(arg.clone()),
+        _ => Err(format!("{} takes one argument, received {:?}", words[0], args)),
+    };
+
+    match words[0].as_str() {
+        "load" => Ok(ScriptCommand::Load(single_arg()?)),
+        "output-file" => Ok(ScriptCommand::OutputFile(single_arg()?)),
+        "compare-to" => Ok(ScriptCommand::CompareTo(single_arg()?)),
+        "output-list" => Ok(ScriptCommand::OutputList(
+            args.iter().map(|arg| parse_column(arg)).collect::<Result<Vec<_>, _>>()?,
+        )),
+        "set" => match args {
+            [register, value] => match value.parse::<i32>() {
+                Ok(value) if (-32768..=65535).contains(&value) => Ok(ScriptCommand::Set(parse_register(register)?, value)),
+                _ => Err(format!("set value must be a 16-bit integer, received {}", value)),
+            },
+            _ => Err(format!("set takes a register and a value, received {:?}", args)),
+        },
+        "tick" => Ok(ScriptCommand::Tick),
+        "tock" => Ok(ScriptCommand::Tock),
+        "ticktock" => Ok(ScriptCommand::TickTock),
+        "output" => Ok(ScriptCommand::Output),
+        "echo" => Ok(ScriptCommand::Echo(args.join(" "))),
+        "clear-echo" => Ok(ScriptCommand::ClearEcho),
+        other => Err(format!("unsupported script command {}", other)),
+    }
+}
+
+fn parse_register(name: &str) -> Result<Register, String> {
+    match name {
+        "A" => Ok(Register::A),
+        "D" => Ok(Register::D),
+        "PC" => Ok(Register::Pc),
+        "time" => Ok(Register::Time),
+        _ => match name.strip_prefix("RAM[").and_then(|rest| rest.strip_suffix(']')) {
+            Some(address) => match address.parse::<u16>() {
+                Ok(address) if address <= 32767 => Ok(Register::Ram(address)),
+                _ => Err(format!("invalid RAM address in {}", name)),
+            },
+            None => Err(format!("unsupported register {}", name)),
+        },
+    }
+}
+
+// `name%Fl.w.r`, the format defaulting to decimal `%D1.6.1`
+fn parse_column(spec: &str) -> Result<OutputColumn, String> {
+    let (name, format) = match spec.find('%') {
+        Some(percent) => (&spec[..percent], &spec[percent + 1..]),
+        None => (spec, "D1.6.1"),
+    };
+
+    let format_char = match format.chars().next() {
+        Some(c) if "DXBS".contains(c) => c,
+        _ => return Err(format!("invalid output format in {}", spec)),
+    };
+
+    let sizes: Vec<usize> = match format[1..].split('.').map(|size| size.parse()).collect() {
+        Ok(sizes) => sizes,
+        Err(_) => return Err(format!("invalid output format in {}", spec)),
+    };
+
+    match sizes.as_slice() {
+        [left, width, right] => Ok(OutputColumn {
+            name: String::from(name),
+            register: parse_register(name)?,
+            format: format_char,
+            left: *left,
+            width: *width,
+            right: *right,
+        }),
+        _ => Err(format!("invalid output format in {}", spec)),
+    }
+}
@@ -0,0 +1,37 @@
+use vmcomp::args;
+use vmcomp::error::Error;
+
+fn message<T: std::fmt::Debug>(result: Result<T, Error>) -> String {
+    match result {
+        Err(Error::Usage(message)) => String::from(message.lines().next().unwrap()),
+        other => panic!("expected a usage error, got {:?}", other),
+    }
+}
+
+#[test]
+fn options_take_inline_values() {
+    assert_eq!(args::split_option("--show=3"), ("--show", Some(String::from("3"))));
+    assert_eq!(args::split_option("--set=0=256"), ("--set", Some(String::from("0=256"))));
+    assert_eq!(args::split_option("--trace"), ("--trace", None));
+    assert_eq!(args::split_option("-o=x"), ("-o=x", None));
+}
+
+#[test]
+fn words_may_be_signed() {
+    assert_eq!(args::parse_word("--set", "-1").unwrap(), 65535);
+    assert_eq!(args::parse_word("--set", "65535").unwrap(), 65535);
+    assert_eq!(message(args::parse_word("--set", "65536")), "--set expects a 16-bit number, received 65536");
+}
+
+#[test]
+fn addresses_stay_within_memory() {
+    assert_eq!(args::parse_range("--show", "32767").unwrap(), 32767..32768);
+    assert_eq!(args::parse_range("--show", "256..32768").unwrap(), 256..32768);
+    assert_eq!(args::parse_assignment("--set", "0=-1").unwrap(), (0, 65535));
+
+    assert_eq!(message(args::parse_range("--show", "65535")), "--show must be an address between 0 and 32767, received 65535");
+    assert_eq!(message(args::parse_range("--show", "-1")), "--show must be an address between 0 and 32767, received -1");
+    assert_eq!(message(args::parse_range("--show", "0..40000")), "--show must be an address between 0 and 32768, received 40000");
+    assert_eq!(message(args::parse_assignment("--set", "32768=1")), "--set must be an address between 0 and 32767, received 32768");
+    assert_eq!(message(args::parse_assignment("--set", "256")), "--set expects ADDRESS=VALUE, received 256");
+}
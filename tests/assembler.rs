@@ -0,0 +1,108 @@
+use vmcomp::assembler;
+
+// The words of `source` in the .hack text format, as the reference assembler writes them
+fn hack(source: &str) -> Vec<String> {
+    assembler::format_hack(&assembler::assemble("Test.asm", source).unwrap()).iter().map(|line| String::from(line.trim_end())).collect()
+}
+
+fn error_codes(source: &str) -> Vec<&'static str> {
+    assembler::assemble("Test.asm", source).unwrap_err().iter().map(|diagnostic| diagnostic.code).collect()
+}
+
+#[test]
+fn assembles_add_like_the_reference_assembler() {
+    assert_eq!(hack("// Computes R0 = 2 + 3\n@2\nD=A\n@3\nD=D+A\n@0\nM=D\n"), [
+        "0000000000000010",
+        "1110110000010000",
+        "0000000000000011",
+        "1110000010010000",
+        "0000000000000000",
+        "1110001100001000",
+    ]);
+}
+
+#[test]
+fn assembles_max_like_the_reference_assembler() {
+    let source = "
+        @R0
+        D=M
+        @R1
+        D=D-M
+        @OUTPUT_FIRST
+        D;JGT
+        @R1
+        D=M
+        @OUTPUT_D
+        0;JMP
+        (OUTPUT_FIRST)
+        @R0
+        D=M
+        (OUTPUT_D)
+        @R2
+        M=D
+        (INFINITE_LOOP)
+        @INFINITE_LOOP
+        0;JMP
+    ";
+
+    assert_eq!(hack(source), [
+        "0000000000000000",
+        "1111110000010000",
+        "0000000000000001",
+        "1111010011010000",
+        "0000000000001010",
+        "1110001100000001",
+        "0000000000000001",
+        "1111110000010000",
+        "0000000000001100",
+        "1110101010000111",
+        "0000000000000000",
+        "1111110000010000",
+        "0000000000000010",
+        "1110001100001000",
+        "0000000000001110",
+        "1110101010000111",
+    ]);
+}
+
+#[test]
+fn encodes_stack_instructions() {
+    assert_eq!(hack("AM=M-1\nM=M+1\nA=D|M\nD=!A\nMD=-1;JLE\n"), [
+        "1111110010101000",
+        "1111110111001000",
+        "1111010101100000",
+        "1110110001010000",
+        "1110111010011110",
+    ]);
+}
+
+#[test]
+fn resolves_predefined_symbols_and_allocates_variables_from_16() {
+    let source = "@SP\n@THAT\n@R15\n@SCREEN\n@KBD\n@i\n@sum\n@i\n@LOOP\n(LOOP)\n";
+    let words = assembler::assemble("Test.asm", source).unwrap();
+
+    assert_eq!(words, [0, 4, 15, 16384, 24576, 16, 17, 16, 9]);
+    assert_eq!(
+        assembler::variables("Test.asm", source).unwrap(),
+        [(String::from("i"), 16), (String::from("sum"), 17)],
+    );
+}
+
+#[test]
+fn rejects_invalid_instructions() {
+    assert_eq!(error_codes("D=X\n"), ["E0010"]);
+    assert_eq!(error_codes("D;JUMP\n"), ["E0010"]);
+    assert_eq!(error_codes("@32768\n"), ["E0010"]);
+    assert_eq!(error_codes("@1abc\n"), ["E0010"]);
+}
+
+#[test]
+fn rejects_symbols_defined_twice() {
+    let errors = assembler::assemble("Test.asm", "(LOOP)\n@LOOP\n0;JMP\n(LOOP)\n").unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, "E0012");
+    assert_eq!(errors[0].message, "symbol LOOP is defined twice");
+    assert_eq!(errors[0].location.as_ref().unwrap().line, 4);
+    assert_eq!(error_codes("(SP)\n"), ["E0012"]);
+}
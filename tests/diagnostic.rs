@@ -0,0 +1,28 @@
+use vmcomp::diagnostic::{Diagnostic, Location};
+use vmcomp::parser;
+
+#[test]
+fn renders_like_rustc() {
+    let source = format!("{}\tpush constant 40000 // too big\n", "\n".repeat(11));
+    let (_, errors) = parser::parse_source("Main.vm", &source);
+
+    assert_eq!(errors[0].to_string(), [
+        "error[E0005]: push constant argument must be between 0 and 32767, received 40000",
+        "  --> Main.vm:12:16",
+        "   |",
+        "12 | \tpush constant 40000 // too big",
+        "   | \t              ^^^^^",
+        "   = help: an A-instruction only holds 15 bits",
+        "",
+    ].join("\n"));
+}
+
+#[test]
+fn renders_single_digit_lines_and_missing_locations() {
+    let location = Location::new("Main.vm", 3, 0..3, "add");
+    let warning = Diagnostic::warning("W0001", String::from("too many statics"), None);
+    let error = Diagnostic::error("E0001", String::from("Unsupported operation: add"), Some(location));
+
+    assert_eq!(warning.to_string(), "warning[W0001]: too many statics\n");
+    assert_eq!(error.to_string(), "error[E0001]: Unsupported operation: add\n --> Main.vm:3:1\n  |\n3 | add\n  | ^^^\n");
+}
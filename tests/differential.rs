@@ -0,0 +1,353 @@
+use vmcomp::differential::{self, Failure, Harness};
+use vmcomp::options::Bootstrap;
+use vmcomp::SourceFile;
+
+fn assert_agree(files: &[(&str, &str)], harness: &Harness) {
+    let files: Vec<SourceFile> = files.iter().map(|(name, source)| SourceFile::new(name, source)).collect();
+
+    if let Err((failure, shrunk)) = differential::check_and_shrink(&files, harness) {
+        let sources: Vec<String> = shrunk.iter().map(|file| format!("// {}\n{}", file.name, file.source)).collect();
+        panic!("{}\nsmallest failing program:\n{}", failure, sources.join("\n"));
+    };
+}
+
+fn assert_program_agrees(source: &str) {
+    assert_agree(&[("Test.vm", source)], &Harness::default());
+}
+
+#[test]
+fn push_constant() {
+    assert_program_agrees("push constant 0\npush constant 7\npush constant 32767\n");
+}
+
+#[test]
+fn push_and_pop_pointer_segments() {
+    assert_program_agrees("
+        push constant 10
+        pop local 0
+        push constant 21
+        push constant 22
+        pop argument 2
+        pop argument 1
+        push constant 36
+        pop this 6
+        push constant 42
+        push constant 45
+        pop that 5
+        pop that 2
+        push constant 510
+        pop local 200
+        push local 0
+        push that 5
+        add
+        push argument 1
+        sub
+        push this 6
+        push this 6
+        add
+        sub
+        push local 200
+        add
+    ");
+}
+
+#[test]
+fn pop_picks_the_shorter_addressing_form() {
+    let instruction_count = |source: &str| vmcomp::compile_source("Test.vm", source).unwrap().instruction_count();
+
+    // Unrolled `A=A+1` chain up to index 4, then a fixed cost through R13
+    assert_eq!(instruction_count("pop local 0"), 8);
+    assert_eq!(instruction_count("pop argument 4"), 12);
+    assert_eq!(instruction_count("pop this 5"), 12);
+    assert_eq!(instruction_count("pop that 200"), 12);
+    assert_eq!(instruction_count("pop that 30000"), 12);
+
+    for index in [0, 1, 4, 5, 6, 250].iter() {
+        assert_program_agrees(&format!("
+            push constant 1234
+            pop local {index}
+            push constant 2345
+            pop argument {index}
+            push constant 3456
+            pop this {index}
+            push constant 4567
+            pop that {index}
+            push local {index}
+            push argument {index}
+            push this {index}
+            push that {index}
+        ", index = index));
+    }
+}
+
+#[test]
+fn push_and_pop_temp() {
+    assert_program_agrees("
+        push constant 11
+        pop temp 0
+        push constant 12
+        pop temp 7
+        push temp 7
+        push temp 0
+        sub
+    ");
+}
+
+#[test]
+fn push_and_pop_pointer() {
+    assert_program_agrees("
+        push constant 3030
+        pop pointer 0
+        push constant 3040
+        pop pointer 1
+        push constant 32
+        pop this 2
+        push constant 46
+        pop that 6
+        push pointer 0
+        push pointer 1
+        add
+    ");
+}
+
+#[test]
+fn push_and_pop_static_across_files() {
+    let harness = Harness::default();
+
+    assert_agree(&[
+        ("First.vm", "push constant 111\npush constant 333\npop static 8\npop static 3\npush static 3\n"),
+        ("Second.vm", "push constant 888\npop static 3\npush static 3\npush static 8\nsub\n"),
+    ], &harness);
+}
+
+#[test]
+fn binary_arithmetic() {
+    assert_program_agrees("
+        push constant 57
+        push constant 31
+        add
+        push constant 53
+        sub
+        push constant 112
+        and
+        push constant 82
+        or
+    ");
+}
+
+#[test]
+fn unary_arithmetic() {
+    assert_program_agrees("push constant 17\nneg\npush constant 0\nnot\npush constant 32767\nneg\nnot\n");
+}
+
+#[test]
+fn comparisons() {
+    assert_program_agrees("
+        push constant 17
+        push constant 17
+        eq
+        push constant 17
+        push constant 16
+        eq
+        push constant 892
+        push constant 891
+        lt
+        push constant 891
+        push constant 892
+        lt
+        push constant 32767
+        push constant 32766
+        gt
+        push constant 32766
+        push constant 32767
+        gt
+        push constant 57
+        neg
+        push constant 12
+        gt
+    ");
+}
+
+// x - y overflows when x and y have opposite signs and are far apart
+#[test]
+fn comparisons_of_opposite_signs() {
+    assert_program_agrees("
+        push constant 20000
+        push constant 20000
+        neg
+        gt
+        push constant 20000
+        push constant 20000
+        neg
+        lt
+        push constant 20000
+        neg
+        push constant 20000
+        gt
+        push constant 20000
+        neg
+        push constant 20000
+        lt
+        push constant 32767
+        push constant 32767
+        neg
+        push constant 1
+        sub
+        gt
+    ");
+}
+
+#[test]
+fn labels_and_jumps() {
+    assert_program_agrees("
+        push constant 0
+        pop local 0
+        push constant 5
+        pop argument 0
+        label LOOP_START
+        push argument 0
+        push local 0
+        add
+        pop local 0
+        push argument 0
+        push constant 1
+        sub
+        pop argument 0
+        push argument 0
+        if-goto LOOP_START
+        goto DONE
+        push constant 999
+        label DONE
+        push local 0
+    ");
+}
+
+const CALLS: &str = "
+        push constant 3
+        push constant 4
+        call Test.multiply 2
+        push constant 7
+        call Test.square 1
+        label END
+        goto END
+
+        function Test.multiply 2
+        push constant 0
+        pop local 0
+        push argument 1
+        pop local 1
+        label LOOP
+        push local 1
+        push constant 0
+        eq
+        if-goto DONE
+        push local 0
+        push argument 0
+        add
+        pop local 0
+        push local 1
+        push constant 1
+        sub
+        pop local 1
+        goto LOOP
+        label DONE
+        push local 0
+        return
+
+        function Test.square 0
+        push argument 0
+        push argument 0
+        call Test.multiply 2
+        return
+    ";
+
+#[test]
+fn function_call_and_return() {
+    assert_program_agrees(CALLS);
+}
+
+#[test]
+fn function_call_and_return_through_shared_routines() {
+    let harness = Harness {
+        options: vmcomp::Options { shared_calls: true, ..Harness::default().options },
+        ..Harness::default()
+    };
+
+    assert_agree(&[("Test.vm", CALLS)], &harness);
+}
+
+const FIBONACCI: [(&str, &str); 2] = [
+    ("Sys.vm", "function Sys.init 0\npush constant 6\ncall Main.fibonacci 1\nlabel WHILE\ngoto WHILE\n"),
+    ("Main.vm", "
+        function Main.fibonacci 0
+        push argument 0
+        push constant 2
+        lt
+        if-goto BASE
+        push argument 0
+        push constant 2
+        sub
+        call Main.fibonacci 1
+        push argument 0
+        push constant 1
+        sub
+        call Main.fibonacci 1
+        add
+        return
+        label BASE
+        push argument 0
+        return
+    "),
+];
+
+fn bootstrap_harness(shared_calls: bool) -> Harness {
+    Harness {
+        options: vmcomp::Options { bootstrap: Bootstrap::Auto, shared_calls, ..vmcomp::Options::default() },
+        ram: Vec::new(),
+        ..Harness::default()
+    }
+}
+
+#[test]
+fn functions_with_many_locals() {
+    assert_program_agrees("
+        call Test.f 0
+        label END
+        goto END
+
+        function Test.f 1000
+        push constant 0
+        return
+    ");
+}
+
+#[test]
+fn recursion_with_bootstrap() {
+    assert_agree(&FIBONACCI, &bootstrap_harness(false));
+}
+
+#[test]
+fn recursion_through_shared_routines() {
+    assert_agree(&FIBONACCI, &bootstrap_harness(true));
+}
+
+#[test]
+fn detects_divergence_and_shrinks_it() {
+    let files = vec![SourceFile::new("Test.vm", "push constant 1\npush constant 2\npush constant 3\nadd\npush constant 4\n")];
+
+    // Stands in for a translator bug triggered by `add`
+    let failing = |files: &[SourceFile]| files[0].source.contains("add") && differential::check(files, &Harness::default()).is_ok();
+    let shrunk = differential::shrink(&files, failing);
+
+    assert_eq!(shrunk[0].source, "add");
+}
+
+#[test]
+fn reports_programs_that_do_not_halt() {
+    let files = vec![SourceFile::new("Test.vm", "label LOOP\npush constant 1\npop temp 0\ngoto LOOP\n")];
+
+    match differential::check(&files, &Harness::default()) {
+        Err(Failure::NoHalt) => (),
+        other => panic!("expected the program not to halt, got {:?}", other.err()),
+    };
+}
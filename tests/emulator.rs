@@ -0,0 +1,47 @@
+use vmcomp::emulator::{Cpu, Stop};
+
+// Sets R0 to 5, then halts on the `(END)` loop at address 4
+const SET_R0: &str = "@5\nD=A\n@R0\nM=D\n(END)\n@END\n0;JMP\n";
+
+fn cpu() -> Cpu {
+    Cpu::from_asm("Test.asm", SET_R0).unwrap()
+}
+
+#[test]
+fn runs_until_the_program_halts() {
+    let mut cpu = cpu();
+
+    assert_eq!(cpu.run(100), Stop::Halted);
+    assert_eq!((cpu.cycles, cpu.pc), (4, 4));
+    assert_eq!(cpu.ram(0), 5);
+}
+
+#[test]
+fn stops_on_breakpoints_before_executing_them() {
+    let mut cpu = cpu();
+    cpu.add_breakpoint(0);
+    cpu.add_breakpoint(2);
+
+    assert_eq!(cpu.run(100), Stop::Breakpoint(0));
+    assert_eq!((cpu.cycles, cpu.pc), (0, 0));
+
+    // Running again resumes the program rather than stopping where it is
+    assert_eq!(cpu.run(100), Stop::Breakpoint(2));
+    assert_eq!((cpu.cycles, cpu.pc, cpu.d), (2, 2, 5));
+
+    assert_eq!(cpu.run(100), Stop::Halted);
+    assert_eq!((cpu.cycles, cpu.pc), (4, 4));
+}
+
+#[test]
+fn stops_when_the_cycle_budget_is_spent() {
+    let mut cpu = cpu();
+
+    assert_eq!(cpu.run(3), Stop::CycleLimit);
+    assert_eq!((cpu.cycles, cpu.pc), (3, 3));
+    assert_eq!(cpu.ram(0), 0);
+
+    assert_eq!(cpu.run(100), Stop::Halted);
+    assert_eq!((cpu.cycles, cpu.pc), (4, 4));
+    assert_eq!(cpu.ram(0), 5);
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::PathBuf;
+
+use vmcomp::manifest::Manifest;
+use vmcomp::{files, Options};
+
+// A fresh directory holding `names`, each an empty .vm file
+fn directory(test_name: &str, names: &[&str]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vmcomp-{}-{}", test_name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    for name in names {
+        fs::write(dir.join(name), "").unwrap();
+    }
+
+    dir
+}
+
+fn names(files: &[vmcomp::SourceFile]) -> Vec<&str> {
+    files.iter().map(|file| file.name.as_str()).collect()
+}
+
+#[test]
+fn directories_are_read_in_name_order() {
+    let dir = directory("order", &["Sys.vm", "Main.vm", "Array.vm", "notes.txt"]);
+
+    assert_eq!(names(&files::read_dir(&dir).unwrap()), ["Array.vm", "Main.vm", "Sys.vm"]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn inputs_set_the_link_order_and_are_read_once() {
+    let dir = directory("inputs", &["Sys.vm", "Main.vm", "Array.vm"]);
+    let inputs = [dir.join("Sys.vm"), dir.clone(), dir.join("Main.vm")];
+
+    assert_eq!(names(&files::read_inputs(&inputs, false).unwrap()), ["Sys.vm", "Array.vm", "Main.vm"]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn the_link_order_is_recorded_in_the_output() {
+    let sources = [vmcomp::SourceFile::new("Sys.vm", ""), vmcomp::SourceFile::new("Main.vm", "")];
+    let asm = vmcomp::compile_program(&sources, &Options::default()).unwrap();
+
+    assert_eq!(asm.lines[0], "// Link order: Sys.vm, Main.vm\n");
+}
+
+#[test]
+fn recursive_inputs_include_subdirectories() {
+    let dir = directory("recursive", &["Main.vm"]);
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("lib").join("Array.vm"), "").unwrap();
+
+    assert_eq!(names(&files::read_inputs(std::slice::from_ref(&dir), false).unwrap()), ["Main.vm"]);
+    assert_eq!(names(&files::read_inputs(std::slice::from_ref(&dir), true).unwrap()), ["Main.vm", "Array.vm"]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn sources_replace_library_files_of_the_same_name() {
+    let dir = directory("libraries", &["Math.vm", "Main.vm"]);
+    let library = dir.join("os");
+    fs::create_dir_all(&library).unwrap();
+    fs::write(library.join("Math.vm"), "").unwrap();
+    fs::write(library.join("Memory.vm"), "").unwrap();
+
+    let sources = files::read_inputs(&[dir.join("Main.vm"), dir.join("Math.vm")], false).unwrap();
+    let libraries = files::read_libraries(&[library], false, &sources).unwrap();
+
+    assert_eq!(names(&libraries), ["Memory.vm"]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn manifest_paths_are_relative_to_the_manifest() {
+    let dir = directory("manifest", &[]);
+    let path = dir.join("vmcomp.toml");
+    fs::write(&path, "sources = [\"src\"]\nentry = \"Main.main\"\n\n[output]\npath = \"Out.asm\"\n").unwrap();
+
+    let manifest = Manifest::read(&path).unwrap();
+
+    assert_eq!(manifest.sources, [dir.join("src")]);
+    assert_eq!(manifest.entry.as_deref(), Some("Main.main"));
+    assert_eq!(manifest.output.path, Some(dir.join("Out.asm")));
+    assert!(!manifest.recursive);
+
+    fs::write(&path, "source = [\"src\"]\n").unwrap();
+    assert!(Manifest::read(&path).is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn the_bootstrap_calls_the_entry_function() {
+    let sources = [vmcomp::SourceFile::new("Main.vm", "function Main.main 0\nlabel END\ngoto END\n")];
+    let options = Options { entry: String::from("Main.main"), ..Options::default() };
+    let asm = vmcomp::compile_program(&sources, &options).unwrap();
+
+    assert!(asm.lines.iter().any(|line| line == "@Main.main\n"));
+    assert!(vmcomp::compile_program(&sources, &Options::default()).unwrap().lines.iter().all(|line| line != "@Main.main\n"));
+}
@@ -0,0 +1,96 @@
+use vmcomp::differential::{self, Harness};
+use vmcomp::emulator::{Cpu, Stop};
+use vmcomp::generator;
+
+const SEEDS: u64 = 300;
+
+fn harness() -> Harness {
+    Harness { max_steps: 1_000_000, ..Harness::default() }
+}
+
+fn describe(seed: u64, files: &[vmcomp::SourceFile]) -> String {
+    let sources: Vec<String> = files.iter().map(|file| format!("// {}\n{}", file.name, file.source)).collect();
+    format!("seed {}:\n{}", seed, sources.join("\n"))
+}
+
+#[test]
+fn generation_is_deterministic() {
+    for seed in 0..10 {
+        let first = generator::generate(seed);
+        let second = generator::generate(seed);
+
+        assert_eq!(describe(seed, &first.files), describe(seed, &second.files));
+    }
+}
+
+#[test]
+fn generated_programs_agree_with_the_interpreter() {
+    let harness = harness();
+
+    for seed in 0..SEEDS {
+        let program = generator::generate(seed);
+
+        if let Err((failure, shrunk)) = differential::check_and_shrink(&program.files, &harness) {
+            panic!("{}\n{}", failure, describe(seed, &shrunk));
+        };
+    }
+}
+
+#[test]
+fn generated_programs_restore_the_caller_frame() {
+    let harness = harness();
+
+    for seed in 0..SEEDS {
+        let program = generator::generate(seed);
+        let asm = vmcomp::compile_program(&program.files, &harness.options).unwrap();
+        let mut cpu = Cpu::from_asm("output.asm", &asm.to_string()).unwrap();
+
+        for (address, value) in harness.ram.iter() {
+            cpu.set_ram(*address, *value);
+        }
+
+        match cpu.run(harness.max_steps * 600) {
+            Stop::Halted => (),
+            stop => panic!("stopped with {:?}\n{}", stop, describe(seed, &program.files)),
+        };
+
+        // Every call returned to the top level code, leaving only its own values on the stack
+        let registers: Vec<u16> = (0..5).map(|address| cpu.ram(address)).collect();
+        assert_eq!(registers, [256 + program.stack_depth, 300, 400, 3000, 3010], "{}", describe(seed, &program.files));
+    }
+}
+
+#[test]
+fn malformed_lines_do_not_panic() {
+    let fragments = [
+        "push", "pop", "constant", "local", "pointer", "temp", "static", "function", "call", "label",
+        "if-goto", "return", "add", "0", "7", "65536", "-1", "Main.f", "Foo", ".", "//", "\t", "é", "",
+    ];
+    let mut rng = generator::Rng::new(0);
+
+    for _ in 0..5000 {
+        let words: Vec<&str> = (0..rng.below(5)).map(|_| *rng.pick(&fragments)).collect();
+        let line = words.join(" ");
+        let name = *rng.pick(&["", "a", ".vm", "Main.vm", "dir/é.vm"]);
+
+        let _ = vmcomp::parser::parse_line(&line);
+
+        if let Err(errors) = vmcomp::compile_source(name, &line) {
+            errors.iter().for_each(|error| drop(error.to_string()));
+        };
+    }
+}
+
+#[test]
+fn generated_programs_agree_through_shared_routines() {
+    let mut harness = harness();
+    harness.options.shared_calls = true;
+
+    for seed in 0..SEEDS {
+        let program = generator::generate(seed);
+
+        if let Err((failure, shrunk)) = differential::check_and_shrink(&program.files, &harness) {
+            panic!("{}\n{}", failure, describe(seed, &shrunk));
+        };
+    }
+}
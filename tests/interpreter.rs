@@ -0,0 +1,35 @@
+use vmcomp::emulator::Stop;
+use vmcomp::interpreter::Vm;
+use vmcomp::{Options, SourceFile};
+
+const SYS: &str = "function Sys.init 0\ncall Main.main 0\nlabel END\ngoto END\n";
+const MAIN: &str = "function Main.main 0\npush constant 7\nreturn\n";
+
+fn vm() -> Vm {
+    Vm::load(&[SourceFile::new("Sys.vm", SYS), SourceFile::new("Main.vm", MAIN)], &Options::default()).unwrap()
+}
+
+#[test]
+fn stops_on_entering_functions_with_breakpoints() {
+    let mut vm = vm();
+    vm.add_breakpoint("Sys.init");
+    vm.add_breakpoint("Main.main");
+
+    // The bootstrap starts on the entry function's own `function` command
+    assert_eq!(vm.run(100), Ok(Stop::Breakpoint(0)));
+    assert_eq!((vm.steps, vm.function_name()), (0, Some("Sys.init")));
+
+    assert_eq!(vm.run(100), Ok(Stop::Breakpoint(4)));
+    assert_eq!((vm.steps, vm.function_name()), (2, Some("Main.main")));
+
+    assert_eq!(vm.run(100), Ok(Stop::Halted));
+    assert_eq!(vm.steps, 6);
+}
+
+#[test]
+fn knows_which_functions_are_defined() {
+    let vm = vm();
+
+    assert!(vm.defines("Main.main"));
+    assert!(!vm.defines("Main.mian"));
+}
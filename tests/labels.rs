@@ -0,0 +1,37 @@
+use vmcomp::{Options, SourceFile};
+
+fn codes(source: &str) -> Vec<&'static str> {
+    match vmcomp::compile_source("Main.vm", source) {
+        Ok(_) => Vec::new(),
+        Err(diagnostics) => diagnostics.iter().map(|diagnostic| diagnostic.code).collect(),
+    }
+}
+
+#[test]
+fn jumps_must_target_a_label_of_their_function() {
+    let source = "function Main.a 0\nlabel LOOP\ngoto LOOP\nfunction Main.b 0\ngoto LOOP\nif-goto LOOP\n";
+    let (_, diagnostics) = vmcomp::translate_program(&[SourceFile::new("Main.vm", source)], &Options::default());
+    let errors: Vec<_> = diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).collect();
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].code, "E0008");
+    assert_eq!(errors[0].message, "label LOOP is not defined in Main.b");
+    assert_eq!(errors[0].location.as_ref().unwrap().line, 5);
+    assert_eq!(errors[1].location.as_ref().unwrap().line, 6);
+}
+
+#[test]
+fn labels_are_defined_once_per_function() {
+    assert_eq!(codes("function Main.a 0\nlabel LOOP\nlabel LOOP\ngoto LOOP\n"), ["E0009"]);
+    assert_eq!(codes("label LOOP\nlabel LOOP\n"), ["E0009"]);
+}
+
+#[test]
+fn functions_can_share_label_names() {
+    let source = "function Main.a 0\nlabel LOOP\ngoto LOOP\nfunction Main.b 0\nlabel LOOP\ngoto LOOP\n";
+    let asm = vmcomp::compile_source("Main.vm", source).unwrap();
+
+    assert!(asm.lines.iter().any(|line| line == "(Main.a$LOOP)\n"));
+    assert!(asm.lines.iter().any(|line| line == "(Main.b$LOOP)\n"));
+    assert!(asm.assemble("Main.asm").is_ok());
+}
@@ -0,0 +1,85 @@
+use vmcomp::options::Bootstrap;
+use vmcomp::{Diagnostic, Options, SourceFile};
+
+const MATH: &str = "function Math.multiply 0\npush argument 0\nreturn\n";
+
+fn diagnostics(files: &[SourceFile], options: &Options) -> Vec<Diagnostic> {
+    vmcomp::translate_program(files, options).1
+}
+
+#[test]
+fn calls_must_target_a_defined_function() {
+    let files = [SourceFile::new("Main.vm", "push constant 2\npush constant 3\ncall Math.multipy 2\n"), SourceFile::new("Math.vm", MATH)];
+    let diagnostics = diagnostics(&files, &Options::default());
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, "E0015");
+    assert_eq!(diagnostics[0].message, "function Math.multipy is not defined");
+    assert_eq!(diagnostics[0].location.as_ref().unwrap().line, 3);
+    assert_eq!(diagnostics[0].help.as_deref(), Some("a function named Math.multiply exists"));
+}
+
+#[test]
+fn functions_must_be_defined_once() {
+    let files = [SourceFile::new("Math.vm", MATH), SourceFile::new("Other.vm", MATH)];
+    let diagnostics = diagnostics(&files, &Options::default());
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, "E0016");
+    assert_eq!(diagnostics[0].location.as_ref().unwrap().file, "Other.vm");
+    assert_eq!(diagnostics[0].help.as_deref(), Some("first defined on line 1 of Math.vm"));
+}
+
+#[test]
+fn warns_when_argument_counts_differ_between_calls() {
+    let main = "push constant 2\npush constant 3\ncall Math.multiply 2\npush constant 4\ncall Math.multiply 1\n";
+    let files = [SourceFile::new("Main.vm", main), SourceFile::new("Math.vm", MATH)];
+    let diagnostics = diagnostics(&files, &Options::default());
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, "W0002");
+    assert_eq!(diagnostics[0].location.as_ref().unwrap().line, 5);
+    assert!(vmcomp::compile_program(&files, &Options::default()).is_ok());
+}
+
+#[test]
+fn the_bootstrap_needs_the_entry_function() {
+    let files = [SourceFile::new("Math.vm", MATH)];
+    let always = Options { bootstrap: Bootstrap::Always, ..Options::default() };
+
+    assert!(diagnostics(&files, &Options::default()).is_empty());
+    assert_eq!(diagnostics(&files, &always)[0].message, "function Sys.init is not defined");
+}
+
+fn removed(files: &[SourceFile], options: &Options) -> Vec<String> {
+    let (asm, _) = vmcomp::translate_program(files, options);
+    asm.removed_functions.into_iter().map(|(function, _)| function).collect()
+}
+
+#[test]
+fn removes_functions_unreachable_from_the_entry() {
+    let files = [
+        SourceFile::new("Sys.vm", "function Sys.init 0\ncall Main.main 0\nlabel END\ngoto END\n"),
+        SourceFile::new("Main.vm", "function Main.main 0\npush constant 3\ncall Math.multiply 1\nreturn\nfunction Main.unused 0\ncall Main.unused 0\nreturn\n"),
+        SourceFile::new("Math.vm", MATH),
+    ];
+    let options = Options { remove_dead_functions: true, ..Options::default() };
+    let asm = vmcomp::compile_program(&files, &options).unwrap();
+
+    assert_eq!(removed(&files, &options), ["Main.unused"]);
+    assert_eq!(asm.lines[1], "// Removed unreachable functions: Main.unused\n");
+    assert!(asm.lines.iter().all(|line| line != "(Main.unused)\n"));
+
+    let keep = Options { keep: vec![String::from("Main.unused")], ..options };
+    assert!(removed(&files, &keep).is_empty());
+}
+
+#[test]
+fn without_bootstrap_the_walk_starts_at_the_first_command() {
+    let top_level = [SourceFile::new("Main.vm", "call Math.multiply 0\nlabel END\ngoto END\n"), SourceFile::new("Math.vm", MATH)];
+    let function_first = [SourceFile::new("Math.vm", MATH), SourceFile::new("Main.vm", "function Main.main 0\nreturn\n")];
+    let options = Options { remove_dead_functions: true, ..Options::default() };
+
+    assert!(removed(&top_level, &options).is_empty());
+    assert_eq!(removed(&function_first, &options), ["Main.main"]);
+}
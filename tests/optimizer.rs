@@ -0,0 +1,68 @@
+use vmcomp::differential::{self, Harness};
+use vmcomp::optimizer;
+use vmcomp::{generator, SourceFile};
+
+fn optimized_harness() -> Harness {
+    Harness { optimize: true, max_steps: 1_000_000, ..Harness::default() }
+}
+
+fn instruction_counts(source: &str) -> (usize, usize) {
+    let asm = vmcomp::compile_source("Test.vm", source).unwrap();
+    (asm.instruction_count(), optimizer::optimize(&asm).instruction_count())
+}
+
+fn assert_agree(source: &str) {
+    let files = vec![SourceFile::new("Test.vm", source)];
+
+    if let Err((failure, shrunk)) = differential::check_and_shrink(&files, &optimized_harness()) {
+        panic!("{}\nsmallest failing program:\n{}", failure, shrunk[0].source);
+    };
+}
+
+#[test]
+fn push_then_binary_operation_keeps_the_value_in_d() {
+    let source = "push constant 7\npush constant 8\nadd\npush constant 3\nsub\n";
+
+    assert_agree(source);
+    assert_eq!(instruction_counts(source), (35, 17));
+}
+
+#[test]
+fn push_then_pop_stores_d_directly() {
+    let source = "push constant 7\npop temp 3\npush constant 0\npop static 1\npush constant 1\npop local 2\n";
+
+    assert_agree(source);
+    assert_eq!(instruction_counts(source), (45, 13));
+}
+
+#[test]
+fn push_then_if_goto_keeps_the_condition_in_d() {
+    let source = "push constant 0\nif-goto SKIP\npush constant 5\npop temp 0\nlabel SKIP\npush constant 1\nif-goto END\npush constant 6\npop temp 1\nlabel END\n";
+
+    assert_agree(source);
+    assert_eq!(instruction_counts(source), (54, 12));
+}
+
+#[test]
+fn unary_operations_are_computed_before_pushing() {
+    let source = "push constant 17\nneg\npush constant 5\nnot\nneg\npush temp 0\nneg\nnot\n";
+
+    assert_agree(source);
+
+    let (before, after) = instruction_counts(source);
+    assert!(after < before, "{} -> {}", before, after);
+}
+
+#[test]
+fn generated_programs_agree_once_optimized() {
+    let harness = optimized_harness();
+
+    for seed in 0..300 {
+        let program = generator::generate(seed);
+
+        if let Err((failure, shrunk)) = differential::check_and_shrink(&program.files, &harness) {
+            let sources: Vec<String> = shrunk.iter().map(|file| format!("// {}\n{}", file.name, file.source)).collect();
+            panic!("seed {}: {}\n{}", seed, failure, sources.join("\n"));
+        };
+    }
+}
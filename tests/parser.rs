@@ -0,0 +1,119 @@
+use vmcomp::command::{ArithmeticCommand, Command, Segment};
+use vmcomp::parser;
+
+fn diagnostic(line: &str) -> (String, String) {
+    let (_, errors) = parser::parse_source("Test.vm", line);
+    assert_eq!(errors.len(), 1, "{}", line);
+
+    (String::from(errors[0].code), errors[0].message.clone())
+}
+
+#[test]
+fn parses_every_command() {
+    let name = |text: &str| String::from(text);
+
+    assert_eq!(parser::parse_line("push constant 7"), Ok(Command::Push(Segment::Constant, 7)));
+    assert_eq!(parser::parse_line("push static 3"), Ok(Command::Push(Segment::Static, 3)));
+    assert_eq!(parser::parse_line("pop pointer 1"), Ok(Command::Pop(Segment::Pointer, 1)));
+    assert_eq!(parser::parse_line("pop temp 6"), Ok(Command::Pop(Segment::Temp, 6)));
+    assert_eq!(parser::parse_line("label LOOP_START"), Ok(Command::Label(name("LOOP_START"))));
+    assert_eq!(parser::parse_line("goto END"), Ok(Command::Goto(name("END"))));
+    assert_eq!(parser::parse_line("if-goto IF_TRUE0"), Ok(Command::IfGoto(name("IF_TRUE0"))));
+    assert_eq!(parser::parse_line("function Main.fibonacci 2"), Ok(Command::Function(name("Main.fibonacci"), 2)));
+    assert_eq!(parser::parse_line("call Math.multiply 2"), Ok(Command::Call(name("Math.multiply"), 2)));
+    assert_eq!(parser::parse_line("return"), Ok(Command::Return));
+
+    for command in ["add", "sub", "neg", "eq", "gt", "lt", "and", "or", "not"].iter() {
+        let arithmetic = ArithmeticCommand::from_name(command).unwrap();
+        assert_eq!(parser::parse_line(command), Ok(Command::Arithmetic(arithmetic)));
+    }
+}
+
+#[test]
+fn skips_comments_and_extra_whitespace() {
+    let source = "// Adds two numbers\n\n   push constant 7   // x\n\tpush\t constant  8\t\nadd//sum\n  \t  \n";
+    let (instructions, errors) = parser::parse_source("Test.vm", source);
+    let commands: Vec<&Command> = instructions.iter().map(|instruction| &instruction.command).collect();
+
+    assert!(errors.is_empty());
+    assert_eq!(commands, [
+        &Command::Push(Segment::Constant, 7),
+        &Command::Push(Segment::Constant, 8),
+        &Command::Arithmetic(ArithmeticCommand::Add),
+    ]);
+
+    // Locations point at the command itself, leaving out indentation and comments
+    assert_eq!(instructions[0].location.line, 3);
+    assert_eq!(instructions[0].location.columns, 3..18);
+    assert_eq!(instructions[1].location.columns, 1..18);
+    assert_eq!(instructions[2].location.columns, 0..3);
+}
+
+#[test]
+fn rejects_unknown_operations() {
+    assert_eq!(
+        diagnostic("mul"),
+        (String::from("E0001"), String::from("Unsupported operation: mul")),
+    );
+    assert_eq!(diagnostic("Push constant 1").0, "E0001");
+}
+
+#[test]
+fn rejects_wrong_argument_counts() {
+    assert_eq!(
+        diagnostic("add 1"),
+        (String::from("E0002"), String::from("add takes no argument, received [\"1\"]")),
+    );
+    assert_eq!(diagnostic("push constant").1, "push takes two arguments, received [\"constant\"]");
+    assert_eq!(diagnostic("goto").1, "goto takes one argument, received []");
+    assert_eq!(diagnostic("return 0").0, "E0002");
+    assert_eq!(diagnostic("call Main.main 0 1").0, "E0002");
+}
+
+#[test]
+fn rejects_popping_into_a_constant() {
+    let (_, errors) = parser::parse_source("Test.vm", "pop constant 0");
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, "E0003");
+    assert_eq!(errors[0].help.as_deref(), Some("to discard the top of the stack, use `pop temp 0`"));
+}
+
+#[test]
+fn accepts_the_full_range_of_constants_and_indices() {
+    assert_eq!(parser::parse_line("push constant 32767"), Ok(Command::Push(Segment::Constant, 32767)));
+    assert_eq!(parser::parse_line("push local 300"), Ok(Command::Push(Segment::Local, 300)));
+    assert_eq!(parser::parse_line("pop argument 32767"), Ok(Command::Pop(Segment::Argument, 32767)));
+    assert_eq!(parser::parse_line("function Main.main 1000"), Ok(Command::Function(String::from("Main.main"), 1000)));
+    assert_eq!(parser::parse_line("call Main.main 300"), Ok(Command::Call(String::from("Main.main"), 300)));
+}
+
+#[test]
+fn rejects_constants_outside_of_15_bits() {
+    assert_eq!(
+        diagnostic("push constant 32768"),
+        (String::from("E0005"), String::from("push constant argument must be between 0 and 32767, received 32768")),
+    );
+    assert_eq!(
+        diagnostic("push constant -1"),
+        (String::from("E0005"), String::from("push constant argument must be between 0 and 32767, received -1")),
+    );
+    assert_eq!(diagnostic("push constant 99999999999999999999999").0, "E0005");
+    assert_eq!(diagnostic("push constant x1").0, "E0004");
+    assert_eq!(diagnostic("push constant -").0, "E0004");
+}
+
+#[test]
+fn rejects_indices_outside_of_their_segment() {
+    assert_eq!(diagnostic("push local 32768").0, "E0005");
+    assert_eq!(diagnostic("pop this -2").0, "E0005");
+    assert_eq!(diagnostic("pop temp 8").1, "pop temp argument must be between 0 and 7, received 8");
+    assert_eq!(diagnostic("push pointer 2").1, "push pointer argument must be 0 or 1, received 2");
+}
+
+#[test]
+fn rejects_counts_outside_of_15_bits() {
+    assert_eq!(diagnostic("call Main.main 40000").1, "call second argument must be between 0 and 32767, received 40000");
+    assert_eq!(diagnostic("function Main.main -1").0, "E0005");
+    assert_eq!(diagnostic("function Main.main many").0, "E0004");
+}
@@ -0,0 +1,59 @@
+use vmcomp::{generator, Options, SourceFile};
+
+fn codes(source: &str) -> Vec<&'static str> {
+    let (_, diagnostics) = vmcomp::translate_program(&[SourceFile::new("Main.vm", source)], &Options::default());
+    diagnostics.iter().map(|diagnostic| diagnostic.code).collect()
+}
+
+#[test]
+fn warns_about_stack_underflow() {
+    assert!(codes("function Main.f 0\npush constant 1\npush constant 2\nadd\nreturn\n").is_empty());
+    assert_eq!(codes("function Main.f 0\npush constant 1\nadd\nreturn\n"), ["W0003"]);
+    assert_eq!(codes("function Main.f 0\ncall Main.f 1\nreturn\n"), ["W0003"]);
+}
+
+#[test]
+fn warns_when_paths_join_with_different_depths() {
+    let balanced = "function Main.f 0\npush argument 0\nif-goto SKIP\npush constant 1\npop temp 0\nlabel SKIP\npush constant 0\nreturn\n";
+    let unbalanced = "function Main.f 0\npush argument 0\nif-goto SKIP\npush constant 1\nlabel SKIP\npush constant 0\nreturn\n";
+
+    assert!(codes(balanced).is_empty());
+    assert_eq!(codes(unbalanced), ["W0004"]);
+}
+
+#[test]
+fn warns_when_a_function_returns_no_value() {
+    assert_eq!(codes("function Main.f 0\nreturn\n"), ["W0005"]);
+}
+
+#[test]
+fn warns_when_the_call_chain_can_overflow_the_stack() {
+    // Each of the n functions keeps 100 locals, plus 5 words of saved frame per call
+    let chain = |n: usize| -> String {
+        let functions: Vec<String> = (0..n)
+            .map(|i| if i + 1 < n {
+                format!("function Sys.f{} 100\ncall Sys.f{} 0\nreturn\n", i, i + 1)
+            } else {
+                format!("function Sys.f{} 100\npush constant 0\nreturn\n", i)
+            })
+            .collect();
+        format!("function Sys.init 0\ncall Sys.f0 0\nlabel END\ngoto END\n{}", functions.concat())
+    };
+
+    let (_, short) = vmcomp::translate_program(&[SourceFile::new("Sys.vm", &chain(10))], &Options::default());
+    let (_, long) = vmcomp::translate_program(&[SourceFile::new("Sys.vm", &chain(20))], &Options::default());
+
+    assert!(short.is_empty());
+    assert_eq!(long.len(), 1);
+    assert_eq!(long[0].message, "the stack can reach RAM[2361], past its end at RAM[2047]");
+    assert!(long[0].help.as_deref().unwrap().starts_with("deepest calls: Sys.init > Sys.f0 > Sys.f1 > "));
+}
+
+#[test]
+fn generated_programs_keep_their_stacks_balanced() {
+    for seed in 0..100 {
+        let (_, diagnostics) = vmcomp::translate_program(&generator::generate(seed).files, &Options::default());
+
+        assert!(diagnostics.is_empty(), "seed {}: {}", seed, diagnostics[0]);
+    }
+}
@@ -0,0 +1,54 @@
+use vmcomp::{Options, SourceFile};
+
+fn statics(name: &str, count: u16) -> SourceFile {
+    let source: Vec<String> = (0..count).map(|index| format!("push constant {0}\npop static {0}\n", index)).collect();
+    SourceFile::new(name, &source.concat())
+}
+
+fn warnings(files: &[SourceFile]) -> Vec<String> {
+    let (_, diagnostics) = vmcomp::translate_program(files, &Options::default());
+    assert!(diagnostics.iter().all(|diagnostic| !diagnostic.is_error()));
+
+    diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect()
+}
+
+#[test]
+fn static_indices_must_be_integers() {
+    let (_, errors) = vmcomp::translate_program(&[SourceFile::new("Main.vm", "push static foo\n")], &Options::default());
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, "E0004");
+}
+
+#[test]
+fn warns_when_a_file_uses_more_than_240_statics() {
+    assert!(warnings(&[statics("Main.vm", 240)]).is_empty());
+    assert_eq!(warnings(&[statics("Main.vm", 241)]), ["Main.vm uses more than 240 static variables"]);
+}
+
+#[test]
+fn warns_when_files_use_more_than_240_statics_together() {
+    assert_eq!(
+        warnings(&[statics("Main.vm", 200), statics("Other.vm", 41)]),
+        ["the program uses 241 static variables, more than the 240 that fit"],
+    );
+}
+
+#[test]
+fn warnings_do_not_fail_the_translation() {
+    assert!(vmcomp::compile_program(&[statics("Main.vm", 241)], &Options::default()).is_ok());
+}
+
+#[test]
+fn reports_the_address_of_each_static() {
+    let files = [
+        SourceFile::new("Main.vm", "push static 3\npop static 0\npush static 3\n"),
+        SourceFile::new("Other.vm", "push static 0\n"),
+    ];
+    let asm = vmcomp::compile_program(&files, &Options::default()).unwrap();
+
+    assert_eq!(
+        asm.static_addresses("out.asm").unwrap(),
+        [(String::from("Main.3"), 16), (String::from("Main.0"), 17), (String::from("Other.0"), 18)],
+    );
+}
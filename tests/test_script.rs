@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use vmcomp::emulator::Cpu;
+use vmcomp::test_script::{self, Mismatch, OutputColumn, Register, ScriptCommand};
+
+const BASIC_TEST_CMP: &str = "\
+|RAM[256]|RAM[300]|RAM[401]|RAM[402]|RAM[3006|RAM[3012|RAM[3015|RAM[11] |
+|    472 |     10 |     21 |     22 |     36 |     42 |     45 |    510 |
+";
+
+const SIMPLE_ADD_CMP: &str = "\
+|  RAM[0]  | RAM[256] |
+|     257  |      15  |
+";
+
+// Runs `script` on an empty program, so that only its `set` commands change RAM
+fn output(script: &str) -> Vec<String> {
+    let commands = test_script::parse_script(&format!("load Test.asm, {}", script)).unwrap();
+    let run = test_script::run_script(&commands, &mut |_| Ok(Cpu::new(&[]))).unwrap();
+
+    run.output
+}
+
+fn column(name: &str, register: Register, format: char, sizes: (usize, usize, usize)) -> OutputColumn {
+    OutputColumn { name: String::from(name), register, format, left: sizes.0, width: sizes.1, right: sizes.2 }
+}
+
+#[test]
+fn parses_a_basic_test_script() {
+    let script = "
+        // This file is part of www.nand2tetris.org
+        /* Tests BasicTest.asm
+           on the CPU emulator. */
+        load BasicTest.asm,
+        output-file BasicTest.out,
+        compare-to BasicTest.cmp,
+        output-list RAM[256]%D1.6.1 RAM[3006]%D1.6.1 RAM[11]%X1.4.1;
+
+        set RAM[0] 256,   // stack pointer
+        set RAM[1] -1,
+
+        echo \"Make sure that 'No Animation' is selected.\";
+        repeat 600 {
+          ticktock;
+        }
+
+        output;
+    ";
+
+    assert_eq!(test_script::parse_script(script).unwrap(), [
+        ScriptCommand::Load(String::from("BasicTest.asm")),
+        ScriptCommand::OutputFile(String::from("BasicTest.out")),
+        ScriptCommand::CompareTo(String::from("BasicTest.cmp")),
+        ScriptCommand::OutputList(vec![
+            column("RAM[256]", Register::Ram(256), 'D', (1, 6, 1)),
+            column("RAM[3006]", Register::Ram(3006), 'D', (1, 6, 1)),
+            column("RAM[11]", Register::Ram(11), 'X', (1, 4, 1)),
+        ]),
+        ScriptCommand::Set(Register::Ram(0), 256),
+        ScriptCommand::Set(Register::Ram(1), -1),
+        ScriptCommand::Echo(String::from("Make sure that 'No Animation' is selected.")),
+        ScriptCommand::Repeat(600, vec![ScriptCommand::TickTock]),
+        ScriptCommand::Output,
+    ]);
+}
+
+#[test]
+fn rejects_malformed_scripts() {
+    assert_eq!(test_script::parse_script("repeat { tick; }").unwrap_err(), "repeat requires a count");
+    assert_eq!(test_script::parse_script("repeat 3 { tick;").unwrap_err(), "missing } to close repeat");
+    assert_eq!(test_script::parse_script("tick; }").unwrap_err(), "unexpected }");
+    assert_eq!(test_script::parse_script("output-list RAM[0]%Q1.6.1;").unwrap_err(), "invalid output format in RAM[0]%Q1.6.1");
+    assert_eq!(test_script::parse_script("set RAM[40000] 1;").unwrap_err(), "invalid RAM address in RAM[40000]");
+}
+
+#[test]
+fn formats_decimal_columns_like_the_cmp_files() {
+    let basic_test = "
+        output-list RAM[256]%D1.6.1 RAM[300]%D1.6.1 RAM[401]%D1.6.1 RAM[402]%D1.6.1
+                    RAM[3006]%D1.6.1 RAM[3012]%D1.6.1 RAM[3015]%D1.6.1 RAM[11]%D1.6.1;
+        set RAM[256] 472, set RAM[300] 10, set RAM[401] 21, set RAM[402] 22,
+        set RAM[3006] 36, set RAM[3012] 42, set RAM[3015] 45, set RAM[11] 510,
+        output;
+    ";
+    let simple_add = "output-list RAM[0]%D2.6.2 RAM[256]%D2.6.2; set RAM[0] 257, set RAM[256] 15, output;";
+
+    assert_eq!(output(basic_test).join("\n") + "\n", BASIC_TEST_CMP);
+    assert_eq!(output(simple_add).join("\n") + "\n", SIMPLE_ADD_CMP);
+    assert_eq!(output("output-list RAM[0]; set RAM[0] -1, output;"), ["| RAM[0] |", "|     -1 |"]);
+}
+
+#[test]
+fn formats_hexadecimal_and_binary_columns() {
+    let script = "output-list RAM[0]%X1.4.1 RAM[1]%B1.16.1; set RAM[0] -1, set RAM[1] 5, output;";
+
+    assert_eq!(output(script), [
+        "|RAM[0]|      RAM[1]      |",
+        "| FFFF | 0000000000000101 |",
+    ]);
+}
+
+#[test]
+fn compare_reports_the_first_mismatching_line() {
+    let output: Vec<String> = SIMPLE_ADD_CMP.lines().map(String::from).collect();
+    let mismatch = |expected: &str| test_script::compare(&output, expected).unwrap_err();
+
+    // Trailing whitespace and blank lines are not differences
+    assert!(test_script::compare(&output, "|  RAM[0]  | RAM[256] |   \n\n|     257  |      15  |\n\n").is_ok());
+
+    assert_eq!(mismatch("|  RAM[0]  | RAM[256] |\n|     258  |      15  |\n"), Mismatch {
+        line: 2,
+        expected: String::from("|     258  |      15  |"),
+        actual: String::from("|     257  |      15  |"),
+    });
+    assert_eq!(mismatch("|  RAM[0]  | RAM[256] |\n").line, 2);
+    assert_eq!(mismatch(&format!("{}|       0  |       0  |\n", SIMPLE_ADD_CMP)).line, 3);
+}
+
+// A fresh directory holding the given files
+fn directory(test_name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vmcomp-script-{}-{}", test_name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    for (name, contents) in files {
+        fs::write(dir.join(name), contents).unwrap();
+    }
+
+    dir
+}
+
+fn ram_after_load(dir: &Path, name: &str, address: u16) -> u16 {
+    let mut cpu = test_script::load(dir, name).unwrap();
+    cpu.run(1000);
+    cpu.ram(address)
+}
+
+#[test]
+fn load_prefers_the_vm_code() {
+    // The stale .asm stores 1 where the VM code stores 7
+    let test_vm = "function Sys.init 0\npush constant 7\npop static 0\nlabel END\ngoto END\n";
+    let stale_asm = "@1\nD=A\n@16\nM=D\n";
+    let dir = directory("vm", &[("Test.vm", test_vm), ("Test.asm", stale_asm)]);
+
+    assert_eq!(ram_after_load(&dir, "Test.asm", 16), 7);
+    fs::remove_dir_all(dir).unwrap();
+
+    let dir = directory("dir", &[("Sys.vm", "function Sys.init 0\npush constant 9\npop static 0\nlabel END\ngoto END\n")]);
+    assert_eq!(ram_after_load(&dir, "FibonacciElement.asm", 16), 9);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn load_falls_back_to_asm_and_hack_files() {
+    let dir = directory("asm", &[("Max.asm", "@5\nD=A\n@0\nM=D\n"), ("Max.hack", "0000000000000110\n1110110000010000\n0000000000000000\n1110001100001000\n")]);
+
+    assert_eq!(ram_after_load(&dir, "Max.asm", 0), 5);
+    assert_eq!(ram_after_load(&dir, "Max.hack", 0), 6);
+    assert!(test_script::load(&dir, "Missing.asm").is_err());
+    fs::remove_dir_all(dir).unwrap();
+}